@@ -0,0 +1,284 @@
+use crate::error::{Error, Result};
+use crate::utils;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Where backups are kept, relative to the project directory
+pub const DIR: &str = ".depup/backups";
+
+/// Generations kept when the configuration does not say otherwise
+pub const DEFAULT_KEEP: usize = 10;
+
+const MANIFEST: &str = "manifest.json";
+const FILES: &str = "files";
+
+// Ecosystems of the same run add to one manifest
+static MANIFEST_LOCK: Mutex<()> = Mutex::new(());
+
+/// The files one run was about to change, as they were before it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Generation {
+    pub id: String,
+    pub created: DateTime<Utc>,
+    pub files: Vec<BackupFile>,
+}
+
+/// A file recorded in a generation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupFile {
+    /// Path relative to the project directory
+    pub path: PathBuf,
+    /// Ecosystem that changed the file, e.g. `npm`
+    pub ecosystem: String,
+    /// SHA-256 of the original contents
+    pub sha256: String,
+    /// SHA-256 of the contents depup left behind, `None` if the file was
+    /// removed or the run was interrupted
+    #[serde(default)]
+    pub written: Option<String>,
+}
+
+impl BackupFile {
+    /// Whether `--only` selects this file, by ecosystem, path or file name
+    pub fn matches(&self, selector: &str) -> bool {
+        self.ecosystem == selector
+            || self.path == Path::new(selector)
+            || self.path.file_name().is_some_and(|name| name == selector)
+    }
+}
+
+/// What a revert did with each selected file.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Revert {
+    /// Put back to the backed-up contents
+    pub restored: Vec<PathBuf>,
+    /// Already identical to the backup
+    pub unchanged: Vec<PathBuf>,
+    /// Changed since depup wrote it, so left alone
+    pub edited: Vec<PathBuf>,
+}
+
+impl Revert {
+    pub fn is_empty(&self) -> bool {
+        self.restored.is_empty() && self.unchanged.is_empty() && self.edited.is_empty()
+    }
+}
+
+/// A new generation id: the UTC time of the run plus a short random suffix
+pub fn run_id() -> String {
+    let now = Utc::now();
+    let salt = now.timestamp_subsec_nanos() ^ std::process::id().rotate_left(16);
+    format!("{}-{:04x}", now.format("%Y%m%dT%H%M%SZ"), salt & 0xffff)
+}
+
+/// Hex-encoded SHA-256 of some contents
+pub fn checksum(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+/// The backup generations of one project, under `.depup/backups`.
+pub struct Store {
+    project: PathBuf,
+}
+
+impl Store {
+    pub fn new(project: &Path) -> Store {
+        Store {
+            project: project.to_path_buf(),
+        }
+    }
+
+    fn dir(&self) -> PathBuf {
+        self.project.join(DIR)
+    }
+
+    /// Copy files into generation `id`, creating it on first use, and return
+    /// where the copies were written; files it already holds keep their copy
+    pub fn back_up(&self, id: &str, ecosystem: &str, files: &[&Path]) -> Result<Vec<PathBuf>> {
+        let _guard = MANIFEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = self.dir().join(id);
+        let mut generation = if dir.join(MANIFEST).exists() {
+            self.generation(id)?
+        } else {
+            Generation {
+                id: id.to_string(),
+                created: Utc::now(),
+                files: Vec::new(),
+            }
+        };
+
+        let mut copies = Vec::new();
+        for file in files {
+            // A file shared with an earlier backup in the generation, such as
+            // a workspace lockfile, keeps the copy from before the run
+            let copy = dir.join(FILES).join(file);
+            if generation.files.iter().any(|f| f.path == *file) {
+                copies.push(copy);
+                continue;
+            }
+
+            let original = self.project.join(file);
+            let contents = fs::read(&original).map_err(|source| Error::Read {
+                path: original,
+                source,
+            })?;
+
+            write_file(&copy, &contents)?;
+            generation.files.push(BackupFile {
+                path: file.to_path_buf(),
+                ecosystem: ecosystem.to_string(),
+                sha256: checksum(&contents),
+                written: None,
+            });
+            copies.push(copy);
+        }
+
+        self.save(&generation)?;
+        Ok(copies)
+    }
+
+    /// Record what an ecosystem's files look like after the update, so a
+    /// revert can tell whether they were edited since
+    pub fn record_written(&self, id: &str, ecosystem: &str) -> Result<()> {
+        let _guard = MANIFEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut generation = self.generation(id)?;
+        for file in &mut generation.files {
+            if file.ecosystem == ecosystem {
+                file.written = fs::read(self.project.join(&file.path))
+                    .ok()
+                    .map(|contents| checksum(&contents));
+            }
+        }
+        self.save(&generation)
+    }
+
+    fn save(&self, generation: &Generation) -> Result<()> {
+        let manifest = serde_json::to_string_pretty(generation).expect("manifests serialize");
+        write_file(
+            &self.dir().join(&generation.id).join(MANIFEST),
+            manifest.as_bytes(),
+        )
+    }
+
+    /// Every generation, oldest first
+    pub fn list(&self) -> Result<Vec<Generation>> {
+        let entries = match fs::read_dir(self.dir()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(source) => {
+                return Err(Error::Read {
+                    path: self.dir(),
+                    source,
+                });
+            }
+        };
+
+        let mut generations = Vec::new();
+        for entry in entries.flatten() {
+            // Skip anything that is not a finished generation
+            if entry.path().join(MANIFEST).is_file() {
+                generations.push(self.generation(&entry.file_name().to_string_lossy())?);
+            }
+        }
+        generations.sort_by(|a, b| (a.created, &a.id).cmp(&(b.created, &b.id)));
+        Ok(generations)
+    }
+
+    /// The generation with the given id
+    pub fn generation(&self, id: &str) -> Result<Generation> {
+        let path = self.dir().join(id).join(MANIFEST);
+        let manifest = match fs::read_to_string(&path) {
+            Ok(manifest) => manifest,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(Error::Backup {
+                    id: id.to_string(),
+                    message: "no such backup".to_string(),
+                });
+            }
+            Err(source) => return Err(Error::Read { path, source }),
+        };
+        serde_json::from_str(&manifest).map_err(|e| Error::Parse {
+            path,
+            message: e.to_string(),
+        })
+    }
+
+    /// The most recent generation, if there is any
+    pub fn latest(&self) -> Result<Option<Generation>> {
+        Ok(self.list()?.pop())
+    }
+
+    /// Put the files of a generation back, or only those `only` selects
+    ///
+    /// Files edited since depup wrote them are left alone unless `force` is
+    /// set. All copies are checked against their checksums before anything
+    /// is written, so a damaged backup restores nothing.
+    pub fn restore(&self, generation: &Generation, only: &[String], force: bool) -> Result<Revert> {
+        let dir = self.dir().join(&generation.id).join(FILES);
+        let selected = generation
+            .files
+            .iter()
+            .filter(|file| only.is_empty() || only.iter().any(|s| file.matches(s)));
+
+        let mut contents = Vec::new();
+        for file in selected {
+            let copy = dir.join(&file.path);
+            let bytes = fs::read(&copy).map_err(|source| Error::Read { path: copy, source })?;
+            if checksum(&bytes) != file.sha256 {
+                return Err(Error::Backup {
+                    id: generation.id.clone(),
+                    message: format!("the copy of {} is corrupted", file.path.display()),
+                });
+            }
+            contents.push((file, bytes));
+        }
+
+        let mut revert = Revert::default();
+        for (file, bytes) in contents {
+            let current = fs::read(self.project.join(&file.path))
+                .ok()
+                .map(|contents| checksum(&contents));
+            if current.as_ref() == Some(&file.sha256) {
+                revert.unchanged.push(file.path.clone());
+            } else if !force && file.written.is_some() && current != file.written {
+                revert.edited.push(file.path.clone());
+            } else {
+                write_file(&self.project.join(&file.path), &bytes)?;
+                revert.restored.push(file.path.clone());
+            }
+        }
+        Ok(revert)
+    }
+
+    /// Delete all but the newest `keep` generations and return their ids
+    pub fn prune(&self, keep: usize) -> Result<Vec<String>> {
+        let generations = self.list()?;
+        let excess = generations.len().saturating_sub(keep);
+
+        let mut removed = Vec::new();
+        for generation in generations.into_iter().take(excess) {
+            let path = self.dir().join(&generation.id);
+            fs::remove_dir_all(&path).map_err(|source| Error::Write { path, source })?;
+            removed.push(generation.id);
+        }
+        Ok(removed)
+    }
+}
+
+fn write_file(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|source| Error::Write {
+            path: parent.to_path_buf(),
+            source,
+        })?;
+    }
+    utils::write_atomic(path, contents).map_err(|source| Error::Write {
+        path: path.to_path_buf(),
+        source,
+    })
+}
@@ -0,0 +1,233 @@
+use crate::ecosystem::Ecosystem;
+use crate::error::{Error, Result};
+use crate::registry::{Registry, Release};
+use crate::{log, utils};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Seconds cached metadata stays fresh when the configuration does not say
+pub const DEFAULT_TTL: u64 = 3600;
+
+/// Environment variable overriding where the cache is kept
+pub const DIR_VAR: &str = "DEPUP_CACHE_DIR";
+
+const REGISTRY: &str = "registry";
+
+/// How registry lookups use the cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Use fresh entries; fetch and store the rest
+    #[default]
+    Normal,
+    /// Fetch everything again, storing what was fetched
+    Refresh,
+    /// Never fetch; use entries of any age
+    Offline,
+}
+
+/// A cached lookup.
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    /// Registry the releases came from
+    registry: String,
+    fetched: DateTime<Utc>,
+    releases: Vec<Release>,
+}
+
+/// What the cache holds for one ecosystem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stats {
+    pub ecosystem: String,
+    pub entries: usize,
+    pub bytes: u64,
+    /// When the least recently fetched entry was fetched
+    pub oldest: Option<DateTime<Utc>>,
+}
+
+/// Registry metadata kept on disk, one file per ecosystem and package.
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn new(dir: &Path) -> Cache {
+        Cache {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// `$DEPUP_CACHE_DIR`, or `depup` in the user cache directory
+    pub fn default_dir() -> PathBuf {
+        match env::var_os(DIR_VAR) {
+            Some(dir) => PathBuf::from(dir),
+            None => dirs::cache_dir()
+                .unwrap_or_else(env::temp_dir)
+                .join("depup"),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, ecosystem: &str, package: &str) -> PathBuf {
+        // Scoped npm packages and Composer vendors contain a slash
+        let name = package.replace('%', "%25").replace('/', "%2F");
+        self.dir
+            .join(REGISTRY)
+            .join(ecosystem)
+            .join(format!("{}.json", name))
+    }
+
+    // The entry for a package, if one from `registry` can be read
+    fn get(&self, ecosystem: &str, registry: &str, package: &str) -> Option<Entry> {
+        let contents = fs::read_to_string(self.path(ecosystem, package)).ok()?;
+        serde_json::from_str::<Entry>(&contents)
+            .ok()
+            .filter(|entry| entry.registry == registry)
+    }
+
+    fn put(&self, ecosystem: &str, package: &str, entry: &Entry) -> Result<()> {
+        let path = self.path(ecosystem, package);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|source| Error::Write {
+                path: parent.to_path_buf(),
+                source,
+            })?;
+        }
+        let contents = serde_json::to_string(entry).expect("cache entries serialize");
+        utils::write_atomic(&path, contents.as_bytes())
+            .map_err(|source| Error::Write { path, source })
+    }
+
+    /// Entries, size and age of the cache, per ecosystem
+    pub fn stats(&self) -> Result<Vec<Stats>> {
+        let mut stats = Vec::new();
+        for dir in read_dir(&self.dir.join(REGISTRY))? {
+            let mut totals = Stats {
+                ecosystem: dir.file_name().to_string_lossy().into_owned(),
+                entries: 0,
+                bytes: 0,
+                oldest: None,
+            };
+            for file in read_dir(&dir.path())? {
+                let Ok(contents) = fs::read_to_string(file.path()) else {
+                    continue;
+                };
+                totals.entries += 1;
+                totals.bytes += contents.len() as u64;
+                if let Ok(entry) = serde_json::from_str::<Entry>(&contents) {
+                    totals.oldest = Some(
+                        totals
+                            .oldest
+                            .map_or(entry.fetched, |oldest| oldest.min(entry.fetched)),
+                    );
+                }
+            }
+            stats.push(totals);
+        }
+        stats.sort_by(|a, b| a.ecosystem.cmp(&b.ecosystem));
+        Ok(stats)
+    }
+
+    /// Delete every entry and return how many there were
+    pub fn clear(&self) -> Result<usize> {
+        let entries = self.stats()?.iter().map(|s| s.entries).sum();
+        let path = self.dir.join(REGISTRY);
+        match fs::remove_dir_all(&path) {
+            Ok(()) => Ok(entries),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+            Err(source) => Err(Error::Write { path, source }),
+        }
+    }
+}
+
+/// Looks releases up in the cache before asking another registry, and keeps
+/// what that registry returns.
+pub struct CachedRegistry<'a> {
+    inner: &'a dyn Registry,
+    cache: Cache,
+    ttl: Duration,
+    mode: Mode,
+}
+
+impl<'a> CachedRegistry<'a> {
+    pub fn new(inner: &'a dyn Registry, cache: Cache, ttl: Duration, mode: Mode) -> Self {
+        CachedRegistry {
+            inner,
+            cache,
+            ttl,
+            mode,
+        }
+    }
+}
+
+impl Registry for CachedRegistry<'_> {
+    fn releases(&self, ecosystem: &dyn Ecosystem, package: &str) -> Result<Vec<Release>> {
+        let registry = self.location(ecosystem);
+        let cached = match self.mode {
+            Mode::Refresh => None,
+            Mode::Normal | Mode::Offline => self.cache.get(ecosystem.name(), &registry, package),
+        };
+
+        let fresh = |entry: &Entry| {
+            (Utc::now() - entry.fetched)
+                .to_std()
+                .is_ok_and(|age| age < self.ttl)
+        };
+        if let Some(entry) = &cached
+            && (self.mode == Mode::Offline || fresh(entry))
+        {
+            return Ok(entry.releases.clone());
+        }
+        if self.mode == Mode::Offline {
+            return Err(Error::Offline {
+                ecosystem: ecosystem.name().to_string(),
+                package: package.to_string(),
+            });
+        }
+
+        match self.inner.releases(ecosystem, package) {
+            Ok(releases) => {
+                let entry = Entry {
+                    registry,
+                    fetched: Utc::now(),
+                    releases,
+                };
+                if let Err(e) = self.cache.put(ecosystem.name(), package, &entry) {
+                    log::debug(&format!("Could not cache {}: {}", package, e));
+                }
+                Ok(entry.releases)
+            }
+            // Old metadata beats none when the registry is unreachable
+            Err(e) => match cached {
+                Some(entry) => {
+                    log::debug(&format!("Using stale metadata for {}: {}", package, e));
+                    Ok(entry.releases)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    fn location(&self, ecosystem: &dyn Ecosystem) -> String {
+        self.inner.location(ecosystem)
+    }
+}
+
+// Entries of a directory, none if it does not exist
+fn read_dir(path: &Path) -> Result<Vec<fs::DirEntry>> {
+    match fs::read_dir(path) {
+        Ok(entries) => Ok(entries.flatten().collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(source) => Err(Error::Read {
+            path: path.to_path_buf(),
+            source,
+        }),
+    }
+}
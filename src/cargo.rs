@@ -1,143 +1,197 @@
-use crate::utils;
-use std::collections::HashMap;
-use std::fs::{copy, read_to_string, write};
+use crate::ecosystem::{
+    Context, Dependency, Ecosystem, Edit, annotation_reason, find_workspace, line_at, parse_error,
+    run_command, run_json,
+};
+use crate::error::Result;
+use crate::registry::{Release, parse_time};
+use crate::report::{Advisory, Audit};
+use crate::rules::{self, Rule};
+use crate::{log, utils};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use toml_edit::{DocumentMut, Formatted, Item, Value};
-
-// Latest versions of common Rust packages
-// In a real implementation, these could be fetched from crates.io API
-const LATEST_VERSIONS: &[(&str, &str)] = &[
-    ("colored", "3.0.0"),
-    ("toml_edit", "0.22.26"),
-    ("serde", "1.0.190"),
-    ("serde_json", "1.0.107"),
-    ("clap", "4.4.8"),
-    ("tempfile", "3.8.1"),
-    ("assert_cmd", "2.0.12"),
-    ("predicates", "3.0.4"),
-];
-
-pub fn update_cargo(backup: bool) {
-    utils::info("Updating Cargo dependencies...");
-
-    // Create backups first if enabled
-    if backup {
-        create_backups();
+use toml_edit::{DocumentMut, Formatted, ImDocument, Item, TableLike, Value};
+
+const SECTIONS: [&str; 2] = ["dependencies", "dev-dependencies"];
+
+/// Rust projects managed with Cargo
+pub struct Cargo;
+
+impl Ecosystem for Cargo {
+    fn name(&self) -> &'static str {
+        "cargo"
     }
 
-    // Read the Cargo.toml file
-    let cargo_toml = match read_to_string("Cargo.toml") {
-        Ok(content) => content,
-        Err(e) => {
-            utils::error(&format!("Failed to read Cargo.toml: {}", e));
-            return;
-        }
-    };
+    fn command(&self) -> &'static str {
+        "cargo"
+    }
 
-    // Parse the TOML file
-    let mut document = match cargo_toml.parse::<DocumentMut>() {
-        Ok(doc) => doc,
-        Err(e) => {
-            utils::error(&format!("Failed to parse Cargo.toml: {}", e));
-            return;
-        }
-    };
+    fn manifest(&self) -> &'static str {
+        "Cargo.toml"
+    }
 
-    // Find outdated packages
-    let outdated_packages = find_outdated_packages(&document);
-    if outdated_packages.is_empty() {
-        utils::info("No outdated cargo packages found.");
-        return;
+    fn lockfile(&self) -> &'static str {
+        "Cargo.lock"
     }
 
-    // Track if we've made any changes
-    let mut updated = false;
-
-    // Update dependencies section
-    if let Some(deps) = document.get_mut("dependencies") {
-        if let Some(deps_table) = deps.as_table_mut() {
-            for (name, (current_version, latest_version)) in &outdated_packages {
-                if let Some(dep) = deps_table.get_mut(name) {
-                    let version_str = format!("^{}", latest_version);
-                    utils::info(&format!(
-                        "Updating {} from {} to {}",
-                        name, current_version, latest_version
-                    ));
-
-                    // Handle different dependency specification formats
-                    match dep {
-                        Item::Value(val) if val.is_str() => {
-                            *val = to_formatted_string(&version_str);
-                            updated = true;
-                        }
-                        Item::Table(table) => {
-                            if let Some(ver) = table.get_mut("version") {
-                                *ver = Item::Value(to_formatted_string(&version_str));
-                                updated = true;
-                            }
-                        }
-                        _ => {
-                            utils::warning(&format!(
-                                "Could not update {} - unsupported dependency format",
-                                name
-                            ));
-                        }
-                    }
+    fn default_registry(&self) -> &'static str {
+        "https://index.crates.io"
+    }
+
+    fn releases_url(&self, registry: &str, package: &str) -> Option<String> {
+        Some(format!("{}/{}", registry, index_path(package)?))
+    }
+
+    fn parse_releases(&self, _package: &str, body: &str) -> Vec<Release> {
+        parse_index(body)
+    }
+
+    // Like Cargo, take the nearest parent with a [workspace] table
+    fn workspace_root(&self, root: &Path) -> Option<PathBuf> {
+        find_workspace(root, self.manifest(), |manifest| {
+            ImDocument::parse(manifest).is_ok_and(|document| document.contains_key("workspace"))
+        })
+    }
+
+    fn collect_dependencies(&self, manifest: &str) -> Result<Vec<Dependency>> {
+        // Parse without discarding spans so declarations can be located
+        let document = ImDocument::parse(manifest).map_err(|e| parse_error(self, e))?;
+
+        let mut dependencies = Vec::new();
+        for section_name in SECTIONS {
+            let Some(table) = document.get(section_name).and_then(|s| s.as_table_like()) else {
+                continue;
+            };
+
+            for (name, item) in table.iter() {
+                // Skip if we couldn't determine the requirement (path or git dependencies)
+                if let Some(requirement) = requirement_of(item) {
+                    let line = span_of(table, name, item).map(|span| line_at(manifest, span.start));
+                    dependencies.push(Dependency {
+                        name: name.to_string(),
+                        requirement: requirement.to_string(),
+                        section: section_name.to_string(),
+                        line,
+                        rules: annotations_of(self, manifest, name, item)?,
+                    });
                 }
             }
         }
+
+        Ok(dependencies)
+    }
+
+    // Cargo cannot list outdated dependencies, so every one is looked up
+    fn lists_outdated(&self) -> bool {
+        false
     }
 
-    // Update dev-dependencies section
-    if let Some(dev_deps) = document.get_mut("dev-dependencies") {
-        if let Some(dev_deps_table) = dev_deps.as_table_mut() {
-            for (name, (current_version, latest_version)) in &outdated_packages {
-                if let Some(dep) = dev_deps_table.get_mut(name) {
-                    let version_str = format!("^{}", latest_version);
-                    utils::info(&format!(
-                        "Updating {} from {} to {}",
-                        name, current_version, latest_version
-                    ));
-
-                    // Handle different dependency specification formats
-                    match dep {
-                        Item::Value(val) if val.is_str() => {
-                            *val = to_formatted_string(&version_str);
-                            updated = true;
-                        }
-                        Item::Table(table) => {
-                            if let Some(ver) = table.get_mut("version") {
-                                *ver = Item::Value(to_formatted_string(&version_str));
-                                updated = true;
-                            }
-                        }
-                        _ => {
-                            utils::warning(&format!(
-                                "Could not update {} - unsupported dependency format",
-                                name
-                            ));
-                        }
+    fn apply(&self, manifest: &str, edits: &[Edit]) -> Result<String> {
+        let mut document = manifest
+            .parse::<DocumentMut>()
+            .map_err(|e| parse_error(self, e))?;
+
+        for edit in edits {
+            let Some(dep) = document
+                .get_mut(&edit.section)
+                .and_then(|s| s.as_table_like_mut())
+                .and_then(|t| t.get_mut(&edit.name))
+            else {
+                continue;
+            };
+
+            // Handle different dependency specification formats; anything else has
+            // no version requirement and was never collected
+            match dep {
+                Item::Value(value @ Value::String(_)) => {
+                    // Keep any surrounding whitespace and comments
+                    let decor = value.decor().clone();
+                    *value = to_formatted_string(&edit.requirement);
+                    *value.decor_mut() = decor;
+                }
+                Item::Value(Value::InlineTable(table)) if table.contains_key("version") => {
+                    table.insert("version", to_formatted_string(&edit.requirement));
+                }
+                Item::Table(table) if table.contains_key("version") => {
+                    // The line may carry an annotation that must survive
+                    if let Some(value) = table["version"].as_value_mut() {
+                        let decor = value.decor().clone();
+                        *value = to_formatted_string(&edit.requirement);
+                        *value.decor_mut() = decor;
                     }
                 }
+                _ => {}
             }
         }
+
+        Ok(document.to_string())
+    }
+
+    fn audit(&self, context: &Context) -> Result<Audit> {
+        // Auditing needs the optional cargo-audit subcommand
+        if !utils::is_command_available("cargo-audit") {
+            log::debug("cargo-audit is not installed; skipping Rust advisories");
+            return Ok(Audit::default());
+        }
+
+        let mut cmd = Command::new("cargo");
+        cmd.args(["audit", "--json"]);
+        Ok(run_json(context, cmd)?
+            .map(|json| parse_audit(&json))
+            .unwrap_or_default())
     }
 
-    if updated {
-        // Write updated Cargo.toml
-        if let Err(e) = write("Cargo.toml", document.to_string()) {
-            utils::error(&format!("Failed to write updated Cargo.toml: {}", e));
-            return;
+    fn refresh_lockfile(&self, context: &Context) -> Result<()> {
+        let mut cmd = Command::new("cargo");
+        cmd.arg("update");
+        if context.offline {
+            cmd.arg("--offline");
+        }
+
+        // Pass --verbose to cargo when its output is logged (-vv)
+        if log::level() >= log::Level::Trace {
+            cmd.arg("--verbose");
         }
 
-        utils::info("Cargo.toml updated with latest dependencies");
+        run_command(context, cmd)
+    }
+}
 
-        // Run cargo update to update the lock file
-        utils::info("Running cargo update...");
-        run_cargo_update();
-    } else {
-        utils::info("No changes needed in Cargo.toml");
+// Location of a crate's file in a sparse registry index, for names made of
+// the ASCII letters, digits, `-` and `_` that crate names are limited to
+fn index_path(package: &str) -> Option<String> {
+    let valid = |b: u8| b.is_ascii_alphanumeric() || b == b'-' || b == b'_';
+    if package.is_empty() || !package.bytes().all(valid) {
+        return None;
     }
+
+    let name = package.to_ascii_lowercase();
+    Some(match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    })
+}
+
+/// Parse a crate's sparse index file: one JSON object per published version
+pub fn parse_index(body: &str) -> Vec<Release> {
+    body.lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter_map(|entry| {
+            Some(Release {
+                published: entry
+                    .get("pubtime")
+                    .and_then(|t| t.as_str())
+                    .and_then(parse_time),
+                yanked: entry
+                    .get("yanked")
+                    .and_then(|y| y.as_bool())
+                    .unwrap_or(false),
+                ..Release::new(entry.get("vers")?.as_str()?)
+            })
+        })
+        .collect()
 }
 
 // Helper function to create formatted TOML strings
@@ -145,122 +199,99 @@ fn to_formatted_string(s: &str) -> Value {
     Value::String(Formatted::new(s.to_string()))
 }
 
-// Extract version constraints without the ^ or ~ prefix
-fn extract_version(version_str: &str) -> String {
-    version_str
-        .trim_start_matches('^')
-        .trim_start_matches('~')
-        .trim_start_matches('=')
-        .trim_start_matches(' ')
-        .to_string()
+// Read the version requirement from a simple string, inline table or table entry
+fn requirement_of(item: &Item) -> Option<&str> {
+    match item {
+        // Simple version string: "package = "1.0""
+        Item::Value(Value::String(s)) => Some(s.value().as_str()),
+        // Table format: "package = { version = "1.0", features = ["derive"] }"
+        Item::Value(Value::InlineTable(table)) => table.get("version").and_then(|v| v.as_str()),
+        Item::Table(table) => table.get("version").and_then(|v| v.as_str()),
+        _ => None,
+    }
 }
 
-fn find_outdated_packages(document: &DocumentMut) -> HashMap<String, (String, String)> {
-    let mut outdated = HashMap::new();
-    utils::debug("Checking for outdated cargo packages");
-
-    // Check dependencies section
-    check_section(document, "dependencies", &mut outdated);
+/// Parse `cargo audit --json`, including its yanked crate warnings
+pub fn parse_audit(json: &serde_json::Value) -> Audit {
+    let entries = |pointer: &str| {
+        json.pointer(pointer)
+            .and_then(|l| l.as_array())
+            .into_iter()
+            .flatten()
+    };
 
-    // Check dev-dependencies section
-    check_section(document, "dev-dependencies", &mut outdated);
+    let advisories = entries("/vulnerabilities/list")
+        .filter_map(|entry| {
+            let advisory = entry.get("advisory")?;
+            let field = |key: &str| {
+                advisory
+                    .get(key)
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+            };
+            Some(Advisory {
+                package: entry.pointer("/package/name")?.as_str()?.to_string(),
+                id: field("id").unwrap_or_default(),
+                title: field("title").unwrap_or_default(),
+                severity: None,
+                url: field("url"),
+            })
+        })
+        .collect();
 
-    if outdated.is_empty() {
-        utils::debug("No outdated cargo packages found");
-    } else {
-        utils::info(&format!("Found {} outdated cargo packages", outdated.len()));
-        for (name, (current, latest)) in &outdated {
-            utils::debug(&format!("  {} {} -> {}", name, current, latest));
-        }
-    }
+    let yanked = entries("/warnings/yanked")
+        .filter_map(|entry| entry.pointer("/package/name")?.as_str())
+        .map(str::to_string)
+        .collect();
 
-    outdated
+    Audit { advisories, yanked }
 }
 
-fn check_section(
-    document: &DocumentMut,
-    section_name: &str,
-    outdated: &mut HashMap<String, (String, String)>,
-) {
-    if let Some(section) = document.get(section_name) {
-        if let Some(table) = section.as_table() {
-            for (name, item) in table.iter() {
-                let current_version = match item {
-                    // Simple version string: "package = "1.0""
-                    Item::Value(val) if val.is_str() => {
-                        val.as_str().map(extract_version).unwrap_or_default()
-                    }
-                    // Table format: "package = { version = "1.0", features = ["derive"] }"
-                    Item::Table(table) => table
-                        .get("version")
-                        .and_then(|v| v.as_str())
-                        .map(extract_version)
-                        .unwrap_or_default(),
-                    _ => continue,
-                };
-
-                // Skip if we couldn't determine the current version
-                if current_version.is_empty() {
-                    continue;
-                }
-
-                // Check if we have a known latest version for this package
-                if let Some((_, latest_version)) =
-                    LATEST_VERSIONS.iter().find(|(pkg, _)| pkg == &name)
-                {
-                    // Compare versions
-                    if !is_up_to_date(&current_version, latest_version) {
-                        utils::info(&format!(
-                            "Found outdated package: {} current: {} latest: {}",
-                            name, current_version, latest_version
-                        ));
-
-                        outdated.insert(
-                            name.to_string(),
-                            (current_version, latest_version.to_string()),
-                        );
-                    }
-                }
-            }
-        }
-    }
-}
+// Rules from `# depup: ...` comments at the end of the dependency's line,
+// or for `[section.name]` tables, of the header or `version` line
+fn annotations_of(cargo: &Cargo, manifest: &str, name: &str, item: &Item) -> Result<Vec<Rule>> {
+    let decors = match item {
+        Item::Value(value) => vec![value.decor()],
+        Item::Table(table) => std::iter::once(table.decor())
+            .chain(
+                table
+                    .get("version")
+                    .and_then(|v| v.as_value())
+                    .map(|v| v.decor()),
+            )
+            .collect(),
+        _ => Vec::new(),
+    };
 
-// Simple version comparison - in a real implementation, this would be more sophisticated
-fn is_up_to_date(current: &str, latest: &str) -> bool {
-    // This is a simplified version check - in reality we would use semver parsing
-    current == latest
-}
+    let mut annotations = Vec::new();
+    for suffix in decors.into_iter().filter_map(|decor| decor.suffix()) {
+        let (comment, span) = match (suffix.as_str(), suffix.span()) {
+            (Some(comment), _) => (comment, None),
+            (None, Some(span)) => (&manifest[span.clone()], Some(span)),
+            (None, None) => continue,
+        };
+        let Some(directive) = rules::annotation(comment) else {
+            continue;
+        };
 
-fn create_backups() {
-    // Create backup of Cargo.toml
-    if copy("Cargo.toml", "Cargo.toml.backup").is_ok() {
-        utils::debug("Created backup: Cargo.toml.backup");
-    } else {
-        utils::warning("Failed to create Cargo.toml backup");
-    }
-
-    // Create backup of Cargo.lock if it exists
-    if std::path::Path::new("Cargo.lock").exists() {
-        if copy("Cargo.lock", "Cargo.lock.backup").is_ok() {
-            utils::debug("Created backup: Cargo.lock.backup");
-        } else {
-            utils::warning("Failed to create Cargo.lock backup");
-        }
-        utils::info("Created backups of Cargo files");
-    } else {
-        utils::info("Created backup of Cargo.toml");
+        let mut rule = rules::parse_annotation(name, directive).map_err(|message| match span {
+            Some(span) => parse_error(
+                cargo,
+                format!("line {}: {}", line_at(manifest, span.start), message),
+            ),
+            None => parse_error(cargo, message),
+        })?;
+        rule.reason = Some(annotation_reason(cargo));
+        annotations.push(rule);
     }
+    Ok(annotations)
 }
 
-fn run_cargo_update() {
-    let mut cmd = Command::new("cargo");
-    cmd.arg("update");
-
-    // Pass --verbose to cargo if our verbose mode is enabled
-    if utils::is_verbose() {
-        cmd.arg("--verbose");
+// Where a dependency is declared: the `version` key of a `[section.name]`
+// table, otherwise the dependency's own key
+fn span_of(table: &dyn TableLike, name: &str, item: &Item) -> Option<Range<usize>> {
+    match item {
+        Item::Table(t) => t.key("version").and_then(|k| k.span()),
+        _ => table.key(name).and_then(|k| k.span()),
     }
-
-    cmd.status().unwrap();
 }
@@ -0,0 +1,288 @@
+use crate::backup;
+use crate::cache;
+use crate::ecosystem::line_at;
+use crate::error::{Error, Result};
+use crate::render::Format;
+use crate::rules::Rule;
+use crate::runner;
+use crate::version::Level;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Name of the project configuration file
+pub const FILE_NAME: &str = ".depup.toml";
+
+/// Settings read from `.depup.toml` and the user configuration file.
+///
+/// Unset values fall back to the built-in defaults, and command line flags
+/// take precedence over both.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// Packages that are never updated, in any ecosystem
+    pub ignore: Vec<String>,
+    /// Largest kind of update to apply
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<Level>,
+    /// Output format when `--format` is not given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<Format>,
+    /// Back up files before changing them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup: Option<bool>,
+    /// Backup generations kept in `.depup/backups`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_backups: Option<usize>,
+    /// Only adopt versions published at least this many days ago
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cooldown: Option<u32>,
+    /// Pre-release channel to adopt, e.g. `beta`, `rc` or npm's `next` tag
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prerelease: Option<String>,
+    /// Seconds a package manager command may run before it is killed; 0
+    /// means no limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Seconds registry metadata is taken from the cache before it is
+    /// fetched again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_ttl: Option<u64>,
+    /// Ignore and pin rules for packages matching a glob
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Rule>,
+    /// Per-ecosystem settings, keyed by ecosystem name, e.g. `npm`
+    pub ecosystems: BTreeMap<String, EcosystemConfig>,
+}
+
+/// Settings for one ecosystem.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct EcosystemConfig {
+    /// Process this ecosystem when it is detected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Packages that are never updated in this ecosystem
+    pub ignore: Vec<String>,
+    /// Registry URL used to look up new versions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// Cooldown in days for this ecosystem, overriding the global one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cooldown: Option<u32>,
+    /// Pre-release channel for this ecosystem, overriding the global one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prerelease: Option<String>,
+    /// Command timeout in seconds for this ecosystem, overriding the global one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Shell commands run in the project after the lockfile is refreshed
+    pub post_update: Vec<String>,
+    /// Shell commands that check the project still works after an update;
+    /// if one fails, the manifest and lockfile are restored
+    pub verify: Vec<String>,
+}
+
+impl Config {
+    /// Layer `other` on top of this configuration: its values win and its
+    /// ignore lists are added to the existing ones
+    pub fn merge(mut self, other: Config) -> Config {
+        extend_unique(&mut self.ignore, other.ignore);
+        self.level = other.level.or(self.level);
+        self.format = other.format.or(self.format);
+        self.backup = other.backup.or(self.backup);
+        self.keep_backups = other.keep_backups.or(self.keep_backups);
+        self.cooldown = other.cooldown.or(self.cooldown);
+        self.prerelease = other.prerelease.or(self.prerelease.take());
+        self.timeout = other.timeout.or(self.timeout);
+        self.cache_ttl = other.cache_ttl.or(self.cache_ttl);
+        self.rules.extend(other.rules);
+
+        for (name, theirs) in other.ecosystems {
+            let ours = self.ecosystems.entry(name).or_default();
+            ours.enabled = theirs.enabled.or(ours.enabled);
+            extend_unique(&mut ours.ignore, theirs.ignore);
+            ours.registry = theirs.registry.or(ours.registry.take());
+            ours.cooldown = theirs.cooldown.or(ours.cooldown);
+            ours.prerelease = theirs.prerelease.or(ours.prerelease.take());
+            ours.timeout = theirs.timeout.or(ours.timeout);
+            if !theirs.post_update.is_empty() {
+                ours.post_update = theirs.post_update;
+            }
+            if !theirs.verify.is_empty() {
+                ours.verify = theirs.verify;
+            }
+        }
+
+        self
+    }
+
+    /// Settings for an ecosystem, empty if it has none
+    pub fn ecosystem(&self, name: &str) -> EcosystemConfig {
+        self.ecosystems.get(name).cloned().unwrap_or_default()
+    }
+
+    /// Whether an ecosystem should be processed
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.ecosystems
+            .get(name)
+            .and_then(|e| e.enabled)
+            .unwrap_or(true)
+    }
+
+    /// Cooldown in days for an ecosystem, `None` when there is none
+    pub fn cooldown_for(&self, ecosystem: &str) -> Option<u32> {
+        self.ecosystems
+            .get(ecosystem)
+            .and_then(|e| e.cooldown)
+            .or(self.cooldown)
+            .filter(|days| *days > 0)
+    }
+
+    /// Pre-release channel for an ecosystem, `None` when only stable
+    /// versions may be adopted
+    pub fn prerelease_for(&self, ecosystem: &str) -> Option<&str> {
+        self.ecosystems
+            .get(ecosystem)
+            .and_then(|e| e.prerelease.as_deref())
+            .or(self.prerelease.as_deref())
+    }
+
+    /// How long an ecosystem's commands may run, `None` for no limit
+    pub fn timeout_for(&self, ecosystem: &str) -> Option<Duration> {
+        let seconds = self
+            .ecosystems
+            .get(ecosystem)
+            .and_then(|e| e.timeout)
+            .or(self.timeout)
+            .unwrap_or(runner::DEFAULT_TIMEOUT);
+        (seconds > 0).then(|| Duration::from_secs(seconds))
+    }
+
+    /// How long cached registry metadata stays fresh
+    pub fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.cache_ttl.unwrap_or(cache::DEFAULT_TTL))
+    }
+
+    /// Every rule for an ecosystem, with its ignore lists as plain rules first
+    pub fn rules_for(&self, ecosystem: &str) -> Vec<Rule> {
+        let ecosystem_ignores = self.ecosystems.get(ecosystem).map(|e| &e.ignore);
+        self.ignore
+            .iter()
+            .chain(ecosystem_ignores.into_iter().flatten())
+            .map(|package| Rule::ignore(package))
+            .chain(
+                self.rules
+                    .iter()
+                    .filter(|rule| rule.ecosystem.as_deref().is_none_or(|e| e == ecosystem))
+                    .cloned(),
+            )
+            .collect()
+    }
+
+    /// Whether a package is ignored outright, globally or for its ecosystem
+    pub fn is_ignored(&self, ecosystem: &str, package: &str) -> bool {
+        self.rules_for(ecosystem)
+            .iter()
+            .any(|rule| rule.matches(ecosystem, package) && rule.check(None).is_some())
+    }
+
+    /// Fill every unset value with its default, for `depup config show`
+    pub fn with_defaults(mut self, ecosystems: &[&str]) -> Config {
+        self.level.get_or_insert_default();
+        self.format.get_or_insert_default();
+        self.backup.get_or_insert(true);
+        self.keep_backups.get_or_insert(backup::DEFAULT_KEEP);
+        self.cooldown.get_or_insert(0);
+        self.timeout.get_or_insert(runner::DEFAULT_TIMEOUT);
+        self.cache_ttl.get_or_insert(cache::DEFAULT_TTL);
+        for name in ecosystems {
+            let ecosystem = self.ecosystems.entry(name.to_string()).or_default();
+            ecosystem.enabled.get_or_insert(true);
+        }
+        self
+    }
+
+    /// Serialize as TOML
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("configuration always serializes")
+    }
+}
+
+/// A configuration together with the files it was read from.
+#[derive(Debug, Clone, Default)]
+pub struct Loaded {
+    pub config: Config,
+    /// Files that were read, lowest precedence first
+    pub sources: Vec<PathBuf>,
+}
+
+/// Load the user configuration and the nearest `.depup.toml` at or above
+/// the project directory, the latter taking precedence
+pub fn load(project: &Path) -> Result<Loaded> {
+    let mut loaded = Loaded::default();
+
+    let files = user_config_path()
+        .filter(|path| path.is_file())
+        .into_iter()
+        .chain(find_project_config(project));
+    for path in files {
+        loaded.config = loaded.config.merge(read(&path)?);
+        loaded.sources.push(path);
+    }
+
+    Ok(loaded)
+}
+
+/// Read a single configuration file
+pub fn read(path: &Path) -> Result<Config> {
+    let content = read_to_string(path).map_err(|source| Error::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let config: Config = toml::from_str(&content).map_err(|e| Error::Parse {
+        path: path.to_path_buf(),
+        message: match e.span() {
+            Some(span) => format!("line {}: {}", line_at(&content, span.start), e.message()),
+            None => e.message().to_string(),
+        },
+    })?;
+
+    let ignores = config
+        .ignore
+        .iter()
+        .chain(config.ecosystems.values().flat_map(|e| &e.ignore))
+        .map(|package| Rule::ignore(package));
+    for rule in ignores.chain(config.rules.iter().cloned()) {
+        rule.validate().map_err(|message| Error::Parse {
+            path: path.to_path_buf(),
+            message,
+        })?;
+    }
+
+    Ok(config)
+}
+
+/// Location of the user configuration file, e.g. `~/.config/depup/config.toml`
+pub fn user_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("depup").join("config.toml"))
+}
+
+/// Find `.depup.toml` in the project directory or the closest ancestor
+pub fn find_project_config(project: &Path) -> Option<PathBuf> {
+    let project = project.canonicalize().ok()?;
+    project
+        .ancestors()
+        .map(|dir| dir.join(FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+fn extend_unique(list: &mut Vec<String>, more: Vec<String>) {
+    for item in more {
+        if !list.contains(&item) {
+            list.push(item);
+        }
+    }
+}
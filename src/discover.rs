@@ -0,0 +1,42 @@
+use crate::ecosystem;
+use crate::log;
+use ignore::WalkBuilder;
+use std::path::{Path, PathBuf};
+
+/// Directories holding installed or built packages, never searched
+pub const SKIPPED: &[&str] = &["node_modules", "vendor", "target"];
+
+/// Every directory under `root` that holds a project of a supported
+/// ecosystem, sorted, with `root` itself first if it is one
+///
+/// Hidden directories and whatever `.gitignore` files exclude are skipped,
+/// as are the directories in [`SKIPPED`].
+pub fn projects(root: &Path) -> Vec<PathBuf> {
+    let ecosystems = ecosystem::registry();
+    let walker = WalkBuilder::new(root)
+        // Honour .gitignore files even outside a git checkout
+        .require_git(false)
+        .filter_entry(|entry| {
+            !(entry.file_type().is_some_and(|t| t.is_dir())
+                && SKIPPED.iter().any(|name| entry.file_name() == *name))
+        })
+        .build();
+
+    let mut projects = Vec::new();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::debug(&format!("Skipping part of the tree: {}", e));
+                continue;
+            }
+        };
+        if entry.file_type().is_some_and(|t| t.is_dir())
+            && ecosystems.iter().any(|e| e.detect(entry.path()))
+        {
+            projects.push(entry.into_path());
+        }
+    }
+    projects.sort();
+    projects
+}
@@ -0,0 +1,1014 @@
+use crate::backup::Store;
+use crate::cache;
+use crate::config::EcosystemConfig;
+use crate::error::{Error, Result};
+use crate::registry::{self, Registry, Release};
+use crate::report::{Audit, DependencyReport, EcosystemReport, ManifestChange, SkipReason};
+use crate::rules::{self, Rule};
+use crate::runner::{CommandRunner, Recorder};
+use crate::transaction::Transaction;
+use crate::update::Options;
+use crate::version;
+use crate::{cargo, jobs, log, node, php};
+use chrono::{Local, TimeDelta};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::slice;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// One lock per project directory, shared by the ecosystems updated in it
+static DIRECTORY_LOCKS: Mutex<BTreeMap<PathBuf, Arc<Mutex<()>>>> = Mutex::new(BTreeMap::new());
+
+/// A dependency as declared in a manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub name: String,
+    /// The version requirement exactly as written, e.g. `^1.0`
+    pub requirement: String,
+    /// The manifest section declaring it, e.g. `dev-dependencies`
+    pub section: String,
+    /// 1-based line of the declaration in the manifest, when known
+    pub line: Option<usize>,
+    /// Policies from inline `depup:` annotations in the manifest
+    pub rules: Vec<Rule>,
+}
+
+/// A newer version found for a dependency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub name: String,
+    pub current: String,
+    pub latest: String,
+}
+
+/// A requirement change to apply to a manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub name: String,
+    pub section: String,
+    pub current: String,
+    pub latest: String,
+    /// The requirement written to the manifest
+    pub requirement: String,
+}
+
+/// Settings and services handed to an ecosystem while it is updated.
+#[derive(Clone, Copy)]
+pub struct Context<'a> {
+    /// Directory of the project being updated; commands run here
+    pub root: &'a Path,
+    /// The ecosystem's section of the configuration
+    pub config: &'a EcosystemConfig,
+    /// Where to look up published versions
+    pub registry: &'a dyn Registry,
+    /// Runs the package manager
+    pub runner: &'a dyn CommandRunner,
+    /// How long a package manager command may take
+    pub timeout: Option<Duration>,
+    /// Package managers must work from their caches, without the network
+    pub offline: bool,
+}
+
+/// A package ecosystem depup knows how to update.
+///
+/// Implementations only describe their manifest format and tooling; the
+/// shared update flow lives in [`update`].
+pub trait Ecosystem: Sync {
+    /// Short identifier, e.g. `cargo`
+    fn name(&self) -> &'static str;
+
+    /// Package manager binary that must be installed
+    fn command(&self) -> &'static str;
+
+    /// Manifest file name, e.g. `Cargo.toml`
+    fn manifest(&self) -> &'static str;
+
+    /// Lockfile name, e.g. `Cargo.lock`
+    fn lockfile(&self) -> &'static str;
+
+    /// Registry used when none is configured, e.g. `https://index.crates.io`
+    fn default_registry(&self) -> &'static str;
+
+    /// URL listing every release of a package in the given registry, or
+    /// `None` when the registry cannot hold a package of that name
+    fn releases_url(&self, registry: &str, package: &str) -> Option<String>;
+
+    /// Parse the registry's response from [`Ecosystem::releases_url`]
+    fn parse_releases(&self, package: &str, body: &str) -> Vec<Release>;
+
+    /// Every file the update may touch
+    fn manifests(&self) -> Vec<&'static str> {
+        vec![self.manifest(), self.lockfile()]
+    }
+
+    /// Whether a dependency is published in the registry, unlike Composer's
+    /// `php` and `ext-*` platform requirements
+    fn is_package(&self, _name: &str) -> bool {
+        true
+    }
+
+    /// Whether a directory contains a project of this ecosystem
+    fn detect(&self, root: &Path) -> bool {
+        root.join(self.manifest()).exists()
+    }
+
+    /// Directory of the workspace the project at `root` is a member of, whose
+    /// lockfile the package manager updates instead of one of its own
+    fn workspace_root(&self, _root: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    /// Parse the manifest and list its dependencies
+    fn collect_dependencies(&self, manifest: &str) -> Result<Vec<Dependency>>;
+
+    /// Whether the package manager can list outdated dependencies; if not,
+    /// every dependency is looked up in the registry instead
+    fn lists_outdated(&self) -> bool {
+        true
+    }
+
+    /// Ask the package manager for newer versions of the given dependencies
+    ///
+    /// Only called when [`Ecosystem::lists_outdated`] holds.
+    fn resolve_candidates(
+        &self,
+        _context: &Context,
+        _dependencies: &[Dependency],
+    ) -> Result<Vec<Candidate>> {
+        Ok(Vec::new())
+    }
+
+    /// Decide which requirements to rewrite
+    fn plan_edits(&self, dependencies: &[Dependency], candidates: &[Candidate]) -> Vec<Edit> {
+        dependencies
+            .iter()
+            .filter_map(|dep| {
+                candidates
+                    .iter()
+                    .find(|candidate| candidate.name == dep.name)
+                    .map(|candidate| Edit {
+                        name: dep.name.clone(),
+                        section: dep.section.clone(),
+                        current: candidate.current.clone(),
+                        latest: candidate.latest.clone(),
+                        requirement: format!("^{}", candidate.latest),
+                    })
+            })
+            .collect()
+    }
+
+    /// Rewrite the manifest contents with the planned edits
+    fn apply(&self, manifest: &str, edits: &[Edit]) -> Result<String>;
+
+    /// Report known security advisories and yanked versions for installed packages
+    fn audit(&self, _context: &Context) -> Result<Audit> {
+        Ok(Audit::default())
+    }
+
+    /// Run the package manager to bring the lockfile in line with the manifest
+    fn refresh_lockfile(&self, context: &Context) -> Result<()>;
+}
+
+/// All supported ecosystems, in the order they are processed
+pub fn registry() -> Vec<Box<dyn Ecosystem>> {
+    vec![
+        Box::new(php::Composer),
+        Box::new(node::Npm),
+        Box::new(cargo::Cargo),
+    ]
+}
+
+/// Run the full update flow for one ecosystem in the project at `root`
+///
+/// In a dry run the flow stops once edits are planned: no backups are made,
+/// the manifest is left untouched and the package manager is not run.
+pub fn update(
+    ecosystem: &dyn Ecosystem,
+    root: &Path,
+    options: &Options,
+    registry: &dyn Registry,
+    runner: &dyn CommandRunner,
+) -> EcosystemReport {
+    let mut report = EcosystemReport::new(ecosystem);
+    let recorder = Recorder::new(runner);
+    if let Err(e) = run_update(ecosystem, root, options, registry, &recorder, &mut report) {
+        report.errors.push(e);
+    }
+    report.commands = recorder.into_outputs();
+
+    // Note what was left behind, so a revert can tell later edits apart
+    if !report.backups.is_empty()
+        && let Err(e) = backup_store(root, options)
+            .0
+            .record_written(&options.run_id, ecosystem.name())
+    {
+        report.warnings.push(format!(
+            "Failed to record the updated {} files: {}",
+            ecosystem.name(),
+            e
+        ));
+    }
+    report
+}
+
+fn run_update(
+    ecosystem: &dyn Ecosystem,
+    root: &Path,
+    options: &Options,
+    registry: &dyn Registry,
+    runner: &dyn CommandRunner,
+    report: &mut EcosystemReport,
+) -> Result<()> {
+    let manifest = ecosystem.manifest();
+    let manifest_path = root.join(manifest);
+    let settings = options.config.ecosystem(ecosystem.name());
+    let context = Context {
+        root,
+        config: &settings,
+        registry,
+        runner,
+        timeout: options.config.timeout_for(ecosystem.name()),
+        offline: options.cache == cache::Mode::Offline,
+    };
+
+    let content = read_to_string(&manifest_path).map_err(|source| Error::Read {
+        path: manifest_path.clone(),
+        source,
+    })?;
+
+    let dependencies = ecosystem.collect_dependencies(&content)?;
+
+    // Expired rules are skipped; point out the ones that still match something
+    let today = options.now.with_timezone(&Local).date_naive();
+    for rule in options.config.rules_for(ecosystem.name()) {
+        if let Some(expired) = rule.expiry_date().filter(|_| rule.is_expired(today))
+            && dependencies
+                .iter()
+                .any(|dep| rule.matches(ecosystem.name(), &dep.name))
+        {
+            report.warnings.push(format!(
+                "Rule for {} expired on {} and no longer applies",
+                rule.package, expired
+            ));
+        }
+    }
+
+    log::debug(&format!(
+        "Checking for outdated {} packages",
+        ecosystem.name()
+    ));
+    let candidates = if options.resolve_from_registry || !ecosystem.lists_outdated() {
+        registry_candidates(ecosystem, &context, options, &dependencies, report)
+    } else {
+        ecosystem.resolve_candidates(&context, &dependencies)?
+    };
+    for candidate in &candidates {
+        log::debug(&format!(
+            "  {} {} -> {}",
+            candidate.name, candidate.current, candidate.latest
+        ));
+    }
+
+    let (targets, skipped) = select_targets(
+        ecosystem,
+        &context,
+        options,
+        &dependencies,
+        &candidates,
+        report,
+    );
+    // Each declaration gets the edit its own policy allows
+    let edits: Vec<Edit> = targets
+        .iter()
+        .flat_map(|(dep, target)| {
+            ecosystem.plan_edits(slice::from_ref(*dep), slice::from_ref(target))
+        })
+        .collect();
+    report.dependencies = dependencies
+        .iter()
+        .map(|dep| dependency_report(ecosystem, options, dep, &candidates, &edits, &skipped))
+        .collect();
+
+    // Advisory databases are not available offline
+    if options.audit && context.offline {
+        report.warnings.push(format!(
+            "Could not audit {} dependencies while offline",
+            ecosystem.name()
+        ));
+    } else if options.audit {
+        match ecosystem.audit(&context) {
+            Ok(audit) => {
+                for dep in &mut report.dependencies {
+                    dep.advisories = audit
+                        .advisories
+                        .iter()
+                        .filter(|a| a.package == dep.name)
+                        .cloned()
+                        .collect();
+                    dep.yanked = audit.yanked.contains(&dep.name);
+                }
+            }
+            Err(e) => report.warnings.push(format!(
+                "Could not audit {} dependencies: {}",
+                ecosystem.name(),
+                e
+            )),
+        }
+    }
+
+    if edits.is_empty() {
+        return Ok(());
+    }
+
+    let updated = ecosystem.apply(&content, &edits)?;
+    if updated == content {
+        return Ok(());
+    }
+
+    report.change = Some(ManifestChange {
+        path: PathBuf::from(manifest),
+        before: content,
+        after: updated.clone(),
+    });
+    if options.dry_run {
+        return Ok(());
+    }
+
+    // Package managers take turns changing the files of one directory, and
+    // workspace members those of their workspace
+    let workspace = ecosystem.workspace_root(root);
+    let directory = lock_directory(workspace.as_deref().unwrap_or(root));
+    let _guard = directory.lock().unwrap_or_else(|e| e.into_inner());
+
+    // Back up only once we know the files will change
+    if options.backup {
+        back_up(ecosystem, root, workspace.as_deref(), options, report);
+    }
+
+    // The manifest and lockfile change together or not at all
+    let files: Vec<PathBuf> = ecosystem
+        .manifests()
+        .into_iter()
+        .map(|file| root.join(file))
+        .chain(workspace.iter().map(|dir| dir.join(ecosystem.lockfile())))
+        .collect();
+    let files: Vec<&Path> = files.iter().map(PathBuf::as_path).collect();
+    let transaction = Transaction::begin(&files)?;
+    transaction.write(&manifest_path, &updated)?;
+    report.manifest_updated = true;
+
+    log::debug(&format!("Running {} update", ecosystem.command()));
+    if let Err(e) = ecosystem.refresh_lockfile(&context) {
+        let failure = format!("{} update failed", ecosystem.command());
+        roll_back(ecosystem, transaction, &failure, report);
+        return Err(e);
+    }
+    report.lockfile_refreshed = true;
+
+    for command in &settings.post_update {
+        log::debug(&format!("Running post-update command: {}", command));
+        if let Err(e) = run_command(&context, shell(command)) {
+            roll_back(
+                ecosystem,
+                transaction,
+                "a post-update command failed",
+                report,
+            );
+            return Err(e);
+        }
+    }
+
+    // The whole batch is undone if the project no longer passes its checks
+    for command in &settings.verify {
+        log::info(&format!(
+            "Verifying the {} update: {}",
+            ecosystem.name(),
+            command
+        ));
+        if let Err(e) = run_command(&context, shell(command)) {
+            roll_back(ecosystem, transaction, "verification failed", report);
+            return Err(Error::Verification {
+                ecosystem: ecosystem.name().to_string(),
+                packages: edits.iter().map(|edit| edit.name.clone()).collect(),
+                source: Box::new(e),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// Put the manifest and lockfile back as they were before the update
+fn roll_back(
+    ecosystem: &dyn Ecosystem,
+    transaction: Transaction,
+    failure: &str,
+    report: &mut EcosystemReport,
+) {
+    match transaction.rollback() {
+        Ok(()) => {
+            report.manifest_updated = false;
+            report.lockfile_refreshed = false;
+            report.rolled_back = true;
+            report.warnings.push(format!(
+                "Restored {} and {} after {}",
+                ecosystem.manifest(),
+                ecosystem.lockfile(),
+                failure
+            ));
+        }
+        Err(e) => report.errors.push(e),
+    }
+}
+
+/// Find newer versions by looking every dependency up in the registry,
+/// measured from the version its requirement names rather than the
+/// installed one, so the result depends on nothing but the manifest and the
+/// registry's answers
+fn registry_candidates(
+    ecosystem: &dyn Ecosystem,
+    context: &Context,
+    options: &Options,
+    dependencies: &[Dependency],
+    report: &mut EcosystemReport,
+) -> Vec<Candidate> {
+    let mut lookups: Vec<(&str, String)> = Vec::new();
+    for dep in dependencies {
+        // Ranges, tags and paths name no single version to compare with
+        if let Some(current) = version::parse(&dep.requirement)
+            && ecosystem.is_package(&dep.name)
+            && !lookups.iter().any(|(name, _)| *name == dep.name)
+        {
+            lookups.push((&dep.name, current.to_string()));
+        }
+    }
+
+    let releases = jobs::map(&lookups, options.jobs, |(name, _)| {
+        context.registry.releases(ecosystem, name)
+    });
+
+    let mut candidates = Vec::new();
+    for ((name, current), releases) in lookups.into_iter().zip(releases) {
+        match releases {
+            Ok(releases) => {
+                if let Some(latest) = registry::latest(&releases)
+                    && version::is_newer(&latest.version, &current)
+                {
+                    candidates.push(Candidate {
+                        name: name.to_string(),
+                        current,
+                        latest: latest.version.clone(),
+                    });
+                }
+            }
+            Err(e) => report
+                .warnings
+                .push(format!("Could not look up {}: {}", name, e)),
+        }
+    }
+    candidates
+}
+
+/// A dependency declaration: its name and the manifest section declaring it
+type Declaration = (String, String);
+
+/// The version a declaration moves to
+type Target<'a> = (&'a Dependency, Candidate);
+
+/// Pick the version each declaration of an outdated dependency moves to
+///
+/// The newest version is used when the policy allows it and no cooldown is
+/// set. Otherwise the registry is asked for the newest release the policy
+/// allows that is older than the cooldown, and the declaration is skipped if
+/// there is none. A package declared in several sections is judged by the
+/// rules of each declaration, but looked up once. Up to [`Options::jobs`]
+/// lookups run at once.
+fn select_targets<'a>(
+    ecosystem: &dyn Ecosystem,
+    context: &Context,
+    options: &Options,
+    dependencies: &'a [Dependency],
+    candidates: &[Candidate],
+    report: &mut EcosystemReport,
+) -> (Vec<Target<'a>>, Vec<(Declaration, SkipReason)>) {
+    let mut targets = Vec::new();
+    let mut skipped = Vec::new();
+    let cooldown = options.config.cooldown_for(ecosystem.name());
+    let channel = options.config.prerelease_for(ecosystem.name());
+    let too_new = |release: &Release| match (cooldown, release.published) {
+        (Some(days), Some(published)) => options.now - published < TimeDelta::days(days.into()),
+        _ => false,
+    };
+    let declaration = |dep: &Dependency| (dep.name.clone(), dep.section.clone());
+
+    // Settle what can be decided without the registry, and collect the rest
+    let allowed = |dep: &Dependency, candidate: &Candidate, release: &Release| {
+        policy_skip(
+            ecosystem,
+            options,
+            dep,
+            Some((&candidate.current, &release.version)),
+        )
+        .or_else(|| prerelease_skip(channel, dep, &candidate.current, release))
+    };
+    let mut pending = Vec::new();
+    for dep in dependencies {
+        let Some(candidate) = candidates.iter().find(|c| c.name == dep.name) else {
+            continue;
+        };
+        match allowed(dep, candidate, &Release::new(&candidate.latest)) {
+            Some(reason @ SkipReason::Ignored { .. }) => {
+                skipped.push((declaration(dep), reason));
+            }
+            // Without a cooldown the newest version's age does not matter,
+            // unless a pre-release channel may hold something newer still
+            None if cooldown.is_none() && channel.is_none() => {
+                targets.push((dep, candidate.clone()))
+            }
+            blocked => pending.push((dep, candidate, blocked)),
+        }
+    }
+
+    // Look for an older version when the newest is ruled out, and for
+    // release dates when a cooldown applies
+    let mut lookups: Vec<&str> = Vec::new();
+    for (_, candidate, _) in &pending {
+        if !lookups.contains(&candidate.name.as_str()) {
+            lookups.push(&candidate.name);
+        }
+    }
+    let releases = jobs::map(&lookups, options.jobs, |name| {
+        context.registry.releases(ecosystem, name)
+    });
+    for (name, releases) in lookups.iter().zip(&releases) {
+        if let Err(e) = releases {
+            report.warnings.push(format!(
+                "Could not look up other versions of {}: {}",
+                name, e
+            ));
+        }
+    }
+
+    for (dep, candidate, blocked) in pending {
+        let cooldown_skip = |published| SkipReason::Cooldown {
+            version: candidate.latest.clone(),
+            days: cooldown.unwrap_or_default(),
+            published,
+        };
+
+        let index = lookups
+            .iter()
+            .position(|name| *name == candidate.name)
+            .expect("every pending package is looked up");
+        let Ok(releases) = &releases[index] else {
+            if blocked.is_none() && cooldown.is_none() {
+                targets.push((dep, candidate.clone()));
+            } else {
+                skipped.push((
+                    declaration(dep),
+                    blocked.unwrap_or_else(|| cooldown_skip(None)),
+                ));
+            }
+            continue;
+        };
+
+        // Never go past the version the package manager reports as latest,
+        // except for pre-releases from the chosen channel
+        let latest = version::parse(&candidate.latest);
+        let eligible = |release: &Release| {
+            allowed(dep, candidate, release).is_none()
+                && !too_new(release)
+                && (latest.as_ref().is_none_or(|latest| {
+                    version::parse(&release.version).is_some_and(|v| v <= *latest)
+                }) || version::is_prerelease(&release.version))
+        };
+
+        match registry::newest(releases, &candidate.current, eligible) {
+            Some(version) => {
+                if version != candidate.latest {
+                    log::debug(&format!(
+                        "  {} {} -> {} instead of {}",
+                        candidate.name, candidate.current, version, candidate.latest
+                    ));
+                }
+                targets.push((
+                    dep,
+                    Candidate {
+                        latest: version,
+                        ..candidate.clone()
+                    },
+                ));
+            }
+            // Nothing from the channel beats the newest version, which is fine
+            None if blocked.is_none() && cooldown.is_none() => {
+                targets.push((dep, candidate.clone()))
+            }
+            None => {
+                let published = releases
+                    .iter()
+                    .find(|release| release.version == candidate.latest)
+                    .and_then(|release| release.published);
+                skipped.push((
+                    declaration(dep),
+                    blocked.unwrap_or_else(|| cooldown_skip(published)),
+                ));
+            }
+        }
+    }
+
+    (targets, skipped)
+}
+
+/// Why a pre-release cannot be adopted, if it cannot: only dependencies
+/// already on a pre-release move to another, unless it is from `channel`,
+/// either by its first identifier (`beta` in `2.0.0-beta.1`) or a dist-tag
+fn prerelease_skip(
+    channel: Option<&str>,
+    dep: &Dependency,
+    current: &str,
+    release: &Release,
+) -> Option<SkipReason> {
+    if !version::is_prerelease(&release.version)
+        || version::is_prerelease(current)
+        || version::is_prerelease(&dep.requirement)
+    {
+        return None;
+    }
+    if let Some(channel) = channel
+        && (version::channel(&release.version).as_deref() == Some(channel)
+            || release.tags.iter().any(|tag| tag == channel))
+    {
+        return None;
+    }
+    Some(SkipReason::Prerelease {
+        version: release.version.clone(),
+    })
+}
+
+/// Why the annotations or configuration rule out updating a package, if
+/// they do; inline annotations are consulted first
+fn policy_skip(
+    ecosystem: &dyn Ecosystem,
+    options: &Options,
+    dep: &Dependency,
+    update: Option<(&str, &str)>,
+) -> Option<SkipReason> {
+    let today = options.now.with_timezone(&Local).date_naive();
+    let rules: Vec<Rule> = dep
+        .rules
+        .iter()
+        .cloned()
+        .chain(options.config.rules_for(ecosystem.name()))
+        .collect();
+    if let Some(reason) = rules::evaluate(&rules, ecosystem.name(), &dep.name, update, today) {
+        return Some(reason);
+    }
+
+    let (current, latest) = update?;
+    let level = rules::level(&rules, ecosystem.name(), &dep.name, today)
+        .or(options.config.level)
+        .unwrap_or_default();
+    if level.allows(current, latest) {
+        return None;
+    }
+    Some(SkipReason::LevelExceeded {
+        update: version::classify(current, latest)?,
+        level,
+    })
+}
+
+fn dependency_report(
+    ecosystem: &dyn Ecosystem,
+    options: &Options,
+    dep: &Dependency,
+    candidates: &[Candidate],
+    edits: &[Edit],
+    skipped: &[(Declaration, SkipReason)],
+) -> DependencyReport {
+    let candidate = candidates.iter().find(|c| c.name == dep.name);
+    let edit = edits
+        .iter()
+        .find(|e| e.name == dep.name && e.section == dep.section);
+    let skip_reason = match edit {
+        Some(_) => None,
+        None => skipped
+            .iter()
+            .find(|((name, section), _)| *name == dep.name && *section == dep.section)
+            .map(|(_, reason)| reason.clone())
+            .or_else(|| policy_skip(ecosystem, options, dep, None))
+            .or(Some(SkipReason::UpToDate)),
+    };
+
+    DependencyReport {
+        name: dep.name.clone(),
+        section: dep.section.clone(),
+        old_requirement: dep.requirement.clone(),
+        new_requirement: edit.map(|e| e.requirement.clone()),
+        current_version: candidate.map(|c| c.current.clone()),
+        latest_version: candidate.map(|c| c.latest.clone()),
+        target_version: edit.map(|e| e.latest.clone()),
+        update_kind: edit.and_then(|e| version::classify(&e.current, &e.latest)),
+        skip_reason,
+        advisories: Vec::new(),
+        yanked: false,
+        line: dep.line,
+    }
+}
+
+/// Build a parse error for the ecosystem's manifest
+pub(crate) fn parse_error(ecosystem: &dyn Ecosystem, message: impl ToString) -> Error {
+    Error::Parse {
+        path: PathBuf::from(ecosystem.manifest()),
+        message: message.to_string(),
+    }
+}
+
+/// Run a package manager command, failing on a non-zero exit status
+///
+/// Output is captured so it cannot interleave with depup's own; it is logged
+/// at trace level and included in the error when the command fails.
+pub(crate) fn run_command(context: &Context, mut cmd: Command) -> Result<()> {
+    cmd.current_dir(context.root);
+    let out = context.runner.run(&mut cmd, context.timeout)?;
+    for line in out.stdout.lines().chain(out.stderr.lines()) {
+        log::trace(line);
+    }
+
+    if out.success() {
+        Ok(())
+    } else {
+        Err(Error::Exit {
+            command: out.command_line(),
+            code: out.code,
+            message: out.stderr.trim().lines().last().map(str::to_string),
+        })
+    }
+}
+
+/// Run a read-only command and parse its JSON output
+///
+/// Commands like `npm outdated` and `npm audit` exit non-zero when they have
+/// something to report, so only failing to start is an error. Returns `None`
+/// when the command printed nothing.
+pub(crate) fn run_json(context: &Context, mut cmd: Command) -> Result<Option<Value>> {
+    cmd.current_dir(context.root);
+    let out = context.runner.run(&mut cmd, context.timeout)?;
+    for line in out.stderr.lines() {
+        log::trace(line);
+    }
+
+    if out.stdout.trim().is_empty() {
+        return Ok(None);
+    }
+
+    serde_json::from_str(&out.stdout)
+        .map(Some)
+        .map_err(|e| Error::Command {
+            command: out.command_line(),
+            message: format!("unexpected output: {}", e),
+        })
+}
+
+/// Build a command that runs a configured command line through the shell
+pub(crate) fn shell(command_line: &str) -> Command {
+    let mut cmd = if cfg!(target_os = "windows") {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    cmd.arg(command_line);
+    cmd
+}
+
+/// List the string-valued entries of the given sections of a JSON manifest
+pub(crate) fn json_dependencies(
+    ecosystem: &dyn Ecosystem,
+    manifest: &str,
+    sections: &[&str],
+) -> Result<Vec<Dependency>> {
+    let json: Value = serde_json::from_str(manifest).map_err(|e| parse_error(ecosystem, e))?;
+    let annotations = json_annotations(ecosystem, &json)?;
+
+    let mut dependencies = Vec::new();
+    for section in sections {
+        if let Some(deps) = json.get(*section).and_then(|v| v.as_object()) {
+            for (name, requirement) in deps {
+                if let Some(requirement) = requirement.as_str() {
+                    dependencies.push(Dependency {
+                        name: name.clone(),
+                        requirement: requirement.to_string(),
+                        section: section.to_string(),
+                        line: find_json_line(manifest, section, name),
+                        rules: annotations
+                            .iter()
+                            .filter(|rule| rule.matches(ecosystem.name(), name))
+                            .cloned()
+                            .collect(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(dependencies)
+}
+
+/// Read the rules in a JSON manifest's `depup` block, found under `extra`
+/// or at the top level:
+///
+/// ```json
+/// "extra": { "depup": { "ignore": ["left-pad"], "pin": { "react": "<19" } } }
+/// ```
+fn json_annotations(ecosystem: &dyn Ecosystem, json: &Value) -> Result<Vec<Rule>> {
+    let Some(block) = json.pointer("/extra/depup").or_else(|| json.get("depup")) else {
+        return Ok(Vec::new());
+    };
+    let invalid =
+        |message: &str| parse_error(ecosystem, format!("invalid depup block: {}", message));
+
+    let mut annotations = Vec::new();
+    if let Some(ignore) = block.get("ignore") {
+        let packages = ignore
+            .as_array()
+            .ok_or_else(|| invalid("`ignore` must be a list of package names"))?;
+        for package in packages {
+            let package = package
+                .as_str()
+                .ok_or_else(|| invalid("`ignore` must be a list of package names"))?;
+            annotations.push(Rule::ignore(package));
+        }
+    }
+    if let Some(pin) = block.get("pin") {
+        let pins = pin
+            .as_object()
+            .ok_or_else(|| invalid("`pin` must map package names to version ranges"))?;
+        for (package, range) in pins {
+            let range = range
+                .as_str()
+                .ok_or_else(|| invalid("`pin` must map package names to version ranges"))?;
+            annotations.push(Rule::pin(package, range));
+        }
+    }
+
+    for rule in &mut annotations {
+        rule.validate().map_err(|e| invalid(&e))?;
+        rule.reason = Some(annotation_reason(ecosystem));
+    }
+    Ok(annotations)
+}
+
+/// Reason recorded for rules that come from manifest annotations
+pub(crate) fn annotation_reason(ecosystem: &dyn Ecosystem) -> String {
+    format!("annotated in {}", ecosystem.manifest())
+}
+
+// serde_json keeps no positions, so look for the quoted section key and then
+// the quoted dependency key after it
+fn find_json_line(manifest: &str, section: &str, name: &str) -> Option<usize> {
+    let section_start = manifest.find(&format!("\"{}\"", section))?;
+    let offset = manifest[section_start..].find(&format!("\"{}\"", name))?;
+    Some(line_at(manifest, section_start + offset))
+}
+
+/// 1-based line number of a byte offset
+pub(crate) fn line_at(content: &str, offset: usize) -> usize {
+    content[..offset].matches('\n').count() + 1
+}
+
+/// Rewrite requirements in a JSON manifest (package.json or composer.json)
+pub(crate) fn apply_json_edits(
+    ecosystem: &dyn Ecosystem,
+    manifest: &str,
+    edits: &[Edit],
+) -> Result<String> {
+    let mut json: Value = serde_json::from_str(manifest).map_err(|e| parse_error(ecosystem, e))?;
+
+    for edit in edits {
+        if let Some(deps) = json.get_mut(&edit.section).and_then(|v| v.as_object_mut())
+            && deps.contains_key(&edit.name)
+        {
+            deps.insert(edit.name.clone(), Value::String(edit.requirement.clone()));
+        }
+    }
+
+    to_json_string(&json, manifest).map_err(|e| parse_error(ecosystem, e))
+}
+
+/// Serialize a JSON manifest using the indentation and trailing newline of
+/// the original file. Key order is kept, but every object and array is laid
+/// out over several lines, so inline ones such as `{"ignore": true}` are
+/// expanded
+fn to_json_string(json: &Value, original: &str) -> serde_json::Result<String> {
+    let indent = original
+        .lines()
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ");
+
+    let mut out = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut out, formatter);
+    json.serialize(&mut serializer)?;
+
+    let mut out = String::from_utf8(out).expect("serde_json produces valid UTF-8");
+    if original.ends_with('\n') {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+// The lock for changing files in a project directory
+fn lock_directory(root: &Path) -> Arc<Mutex<()>> {
+    // Members find their workspace by its absolute path
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    DIRECTORY_LOCKS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(root)
+        .or_default()
+        .clone()
+}
+
+// The nearest directory above `root` with a manifest that `is_workspace`
+// accepts
+pub(crate) fn find_workspace(
+    root: &Path,
+    manifest: &str,
+    is_workspace: impl Fn(&str) -> bool,
+) -> Option<PathBuf> {
+    let root = root.canonicalize().ok()?;
+    root.ancestors()
+        .skip(1)
+        .find(|dir| read_to_string(dir.join(manifest)).is_ok_and(|m| is_workspace(&m)))
+        .map(Path::to_path_buf)
+}
+
+// The store backups go to, and where the project lies within it
+fn backup_store(root: &Path, options: &Options) -> (Store, PathBuf) {
+    match &options.backup_root {
+        Some(top) if root.starts_with(top) => (
+            Store::new(top),
+            root.strip_prefix(top).unwrap_or(root).to_path_buf(),
+        ),
+        _ => (Store::new(root), PathBuf::new()),
+    }
+}
+
+// Copy the manifest and lockfile, or the lockfile of the workspace the
+// project belongs to, into this run's backup generation
+fn back_up(
+    ecosystem: &dyn Ecosystem,
+    root: &Path,
+    workspace: Option<&Path>,
+    options: &Options,
+    report: &mut EcosystemReport,
+) {
+    let (store, prefix) = backup_store(root, options);
+    let mut files: Vec<PathBuf> = ecosystem
+        .manifests()
+        .into_iter()
+        .filter(|file| root.join(file).exists())
+        .map(|file| prefix.join(file))
+        .collect();
+    if let Some(workspace) = workspace
+        && workspace.join(ecosystem.lockfile()).exists()
+    {
+        // The store only holds files below where depup runs
+        let top = options.backup_root.as_deref().unwrap_or(root);
+        match top
+            .canonicalize()
+            .ok()
+            .and_then(|top| Some(workspace.strip_prefix(top).ok()?.join(ecosystem.lockfile())))
+        {
+            Some(lockfile) => files.push(lockfile),
+            None => report.warnings.push(format!(
+                "Could not back up {} of the workspace in {}, outside the backed up directory",
+                ecosystem.lockfile(),
+                workspace.display()
+            )),
+        }
+    }
+    let files: Vec<&Path> = files.iter().map(PathBuf::as_path).collect();
+
+    match store.back_up(&options.run_id, ecosystem.name(), &files) {
+        Ok(copies) => {
+            for copy in &copies {
+                log::debug(&format!("Created backup: {}", copy.display()));
+            }
+            report.backups = copies;
+        }
+        Err(e) => report.warnings.push(format!(
+            "Failed to back up {} files: {}",
+            ecosystem.name(),
+            e
+        )),
+    }
+}
@@ -0,0 +1,153 @@
+use crate::runner::CommandOutput;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Errors produced while updating dependencies.
+#[derive(Debug)]
+pub enum Error {
+    /// A manifest or lockfile could not be read
+    Read { path: PathBuf, source: io::Error },
+    /// A manifest or lockfile could not be written
+    Write { path: PathBuf, source: io::Error },
+    /// A manifest could not be parsed or serialized
+    Parse { path: PathBuf, message: String },
+    /// A package manager command could not be started
+    Command { command: String, message: String },
+    /// A command exited unsuccessfully; `code` is `None` when it was killed
+    Exit {
+        command: String,
+        code: Option<i32>,
+        /// Last line the command wrote to stderr
+        message: Option<String>,
+    },
+    /// A command ran longer than the configured timeout and was killed
+    Timeout {
+        command: String,
+        seconds: u64,
+        /// What the command printed before it was killed
+        output: Box<CommandOutput>,
+    },
+    /// A package registry could not be queried
+    Registry { url: String, message: String },
+    /// Metadata was needed while offline but is not in the cache
+    Offline { ecosystem: String, package: String },
+    /// A package is missing from the registry snapshot the run is limited to
+    Snapshot { ecosystem: String, package: String },
+    /// A backup generation is missing or damaged
+    Backup { id: String, message: String },
+    /// A verification command failed after a batch of updates was applied
+    Verification {
+        ecosystem: String,
+        /// The packages updated in the batch
+        packages: Vec<String>,
+        source: Box<Error>,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Stable identifier for the kind of error, used in machine-readable output
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Read { .. } => "read",
+            Error::Write { .. } => "write",
+            Error::Parse { .. } => "parse",
+            Error::Command { .. } => "command",
+            Error::Exit { .. } => "exit",
+            Error::Timeout { .. } => "timeout",
+            Error::Registry { .. } => "registry",
+            Error::Offline { .. } => "offline",
+            Error::Snapshot { .. } => "snapshot",
+            Error::Backup { .. } => "backup",
+            Error::Verification { .. } => "verification",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Read { path, source } => {
+                write!(f, "Failed to read {}: {}", path.display(), source)
+            }
+            Error::Write { path, source } => {
+                write!(f, "Failed to write {}: {}", path.display(), source)
+            }
+            Error::Parse { path, message } => {
+                write!(f, "Failed to parse {}: {}", path.display(), message)
+            }
+            Error::Command { command, message } => {
+                write!(f, "Failed to run {}: {}", command, message)
+            }
+            Error::Exit {
+                command,
+                code,
+                message,
+            } => {
+                write!(f, "Failed to run {}: ", command)?;
+                match code {
+                    Some(code) => write!(f, "exited with code {}", code)?,
+                    None => write!(f, "killed by a signal")?,
+                }
+                match message {
+                    Some(message) => write!(f, ": {}", message),
+                    None => Ok(()),
+                }
+            }
+            Error::Timeout {
+                command, seconds, ..
+            } => {
+                write!(f, "Failed to run {}: timed out after {}s", command, seconds)
+            }
+            Error::Registry { url, message } => {
+                write!(f, "Failed to fetch {}: {}", url, message)
+            }
+            Error::Offline { ecosystem, package } => write!(
+                f,
+                "No cached {} metadata for {} while offline",
+                ecosystem, package
+            ),
+            Error::Snapshot { ecosystem, package } => write!(
+                f,
+                "No {} metadata for {} in the registry snapshot",
+                ecosystem, package
+            ),
+            Error::Backup { id, message } => {
+                write!(f, "Cannot use backup {}: {}", id, message)
+            }
+            Error::Verification {
+                ecosystem,
+                packages,
+                source,
+            } => write!(
+                f,
+                "Verification failed after updating {} packages {}: {}",
+                ecosystem,
+                packages.join(", "),
+                source
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Read { source, .. } | Error::Write { source, .. } => Some(source),
+            Error::Verification { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Error", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
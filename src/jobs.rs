@@ -0,0 +1,59 @@
+use crate::log;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Jobs run at once when `--jobs` is not given: one per CPU
+pub fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// Apply `f` to every item on up to `jobs` threads, returning the results in
+/// the order of the items
+///
+/// What each job logs is printed in one piece as it finishes. If the caller
+/// is holding back its own output, the jobs' lines join it in item order.
+pub fn map<T, R, F>(items: &[T], jobs: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    if jobs <= 1 || items.len() <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let nested = log::is_holding();
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<_>>());
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(items.len()) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(index) else {
+                        break;
+                    };
+                    let (result, mut lines) = log::hold(|| f(item));
+                    if !nested {
+                        log::release(std::mem::take(&mut lines));
+                    }
+                    results.lock().unwrap_or_else(|e| e.into_inner())[index] =
+                        Some((result, lines));
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .map(|result| {
+            let (result, lines) = result.expect("every item is processed");
+            log::release(lines);
+            result
+        })
+        .collect()
+}
@@ -1,5 +1,31 @@
 // Export modules for testing
+pub mod backup;
+pub mod cache;
 pub mod cargo;
+pub mod config;
+pub mod diff;
+pub mod discover;
+pub mod ecosystem;
+pub mod error;
+pub mod jobs;
+pub mod log;
 pub mod node;
 pub mod php;
+pub mod registry;
+pub mod render;
+pub mod report;
+pub mod rules;
+pub mod runner;
+pub mod snapshot;
+pub mod transaction;
+pub mod update;
 pub mod utils;
+pub mod version;
+
+pub use config::Config;
+pub use error::{Error, Result};
+pub use report::{
+    Advisory, Audit, DependencyReport, EcosystemReport, ManifestChange, Outcome, SkipReason,
+    UpdateReport,
+};
+pub use update::{Options, run, run_with};
@@ -0,0 +1,223 @@
+use chrono::{Local, SecondsFormat};
+use clap::ValueEnum;
+use std::cell::RefCell;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+/// How much detail to log, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warning,
+    Info,
+    Debug,
+    /// Registry requests and everything package managers print
+    Trace,
+}
+
+impl Level {
+    /// The level for `-q` and the number of `-v` flags given
+    pub fn from_flags(quiet: bool, verbose: u8) -> Level {
+        match (quiet, verbose) {
+            (true, _) => Level::Error,
+            (false, 0) => Level::Info,
+            (false, 1) => Level::Debug,
+            (false, _) => Level::Trace,
+        }
+    }
+
+    fn from_u8(level: u8) -> Level {
+        match level {
+            0 => Level::Error,
+            1 => Level::Warning,
+            2 => Level::Info,
+            3 => Level::Debug,
+            _ => Level::Trace,
+        }
+    }
+}
+
+/// When to color messages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Color {
+    /// When writing to a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// A logged message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub level: Level,
+    /// Label shown before the message, e.g. `SUCCESS`
+    pub label: &'static str,
+    pub message: String,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+static COLOR: AtomicBool = AtomicBool::new(false);
+static FILE: Mutex<Option<File>> = Mutex::new(None);
+
+// Keeps blocks of held-back messages from interleaving
+static OUTPUT: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    // Messages held back while this thread works on one job
+    static HELD: RefCell<Option<Vec<Record>>> = const { RefCell::new(None) };
+}
+
+/// Set up logging: messages up to `level` go to stderr, and everything up to
+/// debug detail (or `level`, if more) is appended to `file`
+pub fn init(level: Level, color: Color, file: Option<&Path>) -> io::Result<()> {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    COLOR.store(
+        match color {
+            Color::Always => true,
+            Color::Never => false,
+            Color::Auto => !no_color && io::stderr().is_terminal(),
+        },
+        Ordering::Relaxed,
+    );
+    // Diffs on stdout follow an explicit choice; `auto` leaves them to
+    // their own terminal check, which also honours NO_COLOR
+    match color {
+        Color::Always => colored::control::set_override(true),
+        Color::Never => colored::control::set_override(false),
+        Color::Auto => {}
+    }
+
+    if let Some(path) = file {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        *FILE.lock().unwrap_or_else(|e| e.into_inner()) = Some(file);
+    }
+    Ok(())
+}
+
+/// The most detailed level shown on stderr
+pub fn level() -> Level {
+    Level::from_u8(LEVEL.load(Ordering::Relaxed))
+}
+
+pub fn error(message: &str) {
+    log(Level::Error, "ERROR", message);
+}
+
+pub fn warning(message: &str) {
+    log(Level::Warning, "WARNING", message);
+}
+
+pub fn info(message: &str) {
+    log(Level::Info, "INFO", message);
+}
+
+pub fn success(message: &str) {
+    log(Level::Info, "SUCCESS", message);
+}
+
+pub fn debug(message: &str) {
+    log(Level::Debug, "DEBUG", message);
+}
+
+pub fn trace(message: &str) {
+    log(Level::Trace, "TRACE", message);
+}
+
+fn log(level: Level, label: &'static str, message: &str) {
+    let record = Record {
+        level,
+        label,
+        message: message.to_string(),
+    };
+    let record = HELD.with(|held| match held.borrow_mut().as_mut() {
+        Some(records) => {
+            records.push(record);
+            None
+        }
+        None => Some(record),
+    });
+    if let Some(record) = record {
+        let _guard = OUTPUT.lock().unwrap_or_else(|e| e.into_inner());
+        write(&record);
+    }
+}
+
+fn write(record: &Record) {
+    let shown = level();
+    if record.level <= shown {
+        let label = format!("[{}]", record.label);
+        if COLOR.load(Ordering::Relaxed) {
+            eprintln!(
+                "\x1b[{}m{}\x1b[0m {}",
+                color_code(record),
+                label,
+                record.message
+            );
+        } else {
+            eprintln!("{} {}", label, record.message);
+        }
+    }
+
+    if record.level <= shown.max(Level::Debug)
+        && let Some(file) = FILE.lock().unwrap_or_else(|e| e.into_inner()).as_mut()
+    {
+        // A log file that cannot be written to must not stop the run
+        let _ = writeln!(
+            file,
+            "{} {:<7} {}",
+            Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+            record.label,
+            record.message
+        );
+    }
+}
+
+// ANSI color of a record's label
+fn color_code(record: &Record) -> u8 {
+    match record.label {
+        "SUCCESS" => 32,
+        _ => match record.level {
+            Level::Error => 31,
+            Level::Warning => 33,
+            Level::Info => 34,
+            Level::Debug => 35,
+            Level::Trace => 90,
+        },
+    }
+}
+
+/// Whether this thread is holding back its messages
+pub fn is_holding() -> bool {
+    HELD.with(|held| held.borrow().is_some())
+}
+
+/// Run `f` with its messages held back, returning them with its result
+pub fn hold<R>(f: impl FnOnce() -> R) -> (R, Vec<Record>) {
+    let outer = HELD.with(|held| held.replace(Some(Vec::new())));
+    let result = f();
+    let records = HELD.with(|held| held.replace(outer)).unwrap_or_default();
+    (result, records)
+}
+
+/// Log messages held back by [`hold`] in one piece, or hold them in turn if
+/// this thread is holding its own messages
+pub fn release(records: Vec<Record>) {
+    let records = HELD.with(|held| match held.borrow_mut().as_mut() {
+        Some(held) => {
+            held.extend(records);
+            Vec::new()
+        }
+        None => records,
+    });
+    let _guard = OUTPUT.lock().unwrap_or_else(|e| e.into_inner());
+    for record in &records {
+        write(record);
+    }
+}
@@ -1,137 +1,762 @@
-mod cargo;
-mod node;
-mod php;
-mod utils;
-
-use clap::Parser;
-use std::path::PathBuf;
+use chrono::{Local, Utc};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser, Subcommand};
+use depup::backup::{self, Store};
+use depup::cache::{self, Cache, CachedRegistry};
+use depup::log::{self, Color};
+use depup::registry::HttpRegistry;
+use depup::render::{self, Format};
+use depup::snapshot::{Recording, Snapshot};
+use depup::version::Level;
+use depup::{Options, Outcome, SkipReason, UpdateReport, config, diff, ecosystem, jobs, utils};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
 #[derive(Parser, Debug)]
 #[command(
     about = "Dependency Update Tool",
     version,
     author = "Zander <zander@zanderlewis.dev>",
-    long_about = "A utility for updating dependencies."
+    long_about = "A utility for updating dependencies.",
+    override_usage = "depup [OPTIONS] [PATH]\n       depup [OPTIONS] <COMMAND>"
 )]
 struct Cli {
     /// Skip creating backups of package files before updating
     #[arg(short = 'B', long = "no-backup")]
     no_backup: bool,
 
-    /// Enable verbose output
-    #[arg(short, long)]
-    verbose: bool,
+    /// Log more detail: `-v` for debug messages, `-vv` also for registry
+    /// requests and package manager output
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Only log errors
+    #[arg(short, long, conflicts_with = "verbose", global = true)]
+    quiet: bool,
+
+    /// When to color messages; `auto` colors them on a terminal unless
+    /// NO_COLOR is set
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        default_value_t = Color::Auto,
+        global = true
+    )]
+    color: Color,
+
+    /// Also append log messages, with debug detail, to this file
+    #[arg(long, value_name = "FILE", global = true)]
+    log_file: Option<PathBuf>,
 
     /// Path to the project directory
     #[arg(default_value = ".")]
     path: PathBuf,
 
-    /// Revert changes with the backup files
+    /// Revert the most recent update from its backup, like `depup revert`
     #[arg(short = 'r', long)]
     revert: bool,
+
+    /// Show the updates that would be made without changing any files
+    #[arg(short = 'n', long, conflicts_with = "revert")]
+    dry_run: bool,
+
+    /// Show a diff of every manifest change
+    #[arg(short, long, conflicts_with = "revert")]
+    diff: bool,
+
+    /// Check for outdated or vulnerable dependencies without changing any files.
+    /// Exits with 3 if updates are available, 4 if a dependency has a security
+    /// advisory or a yanked version and 1 on errors
+    #[arg(short, long, conflicts_with = "revert")]
+    check: bool,
+
+    /// Largest update to apply; newer versions beyond it are passed over for
+    /// the newest one within it [default: major]
+    #[arg(short, long, value_enum)]
+    level: Option<Level>,
+
+    /// Only adopt versions published at least this many days ago [default: 0]
+    #[arg(long, value_name = "DAYS")]
+    cooldown: Option<u32>,
+
+    /// Also adopt pre-releases from this channel, e.g. `beta`, `rc` or an npm
+    /// dist-tag such as `next`
+    #[arg(long, value_name = "CHANNEL")]
+    prerelease: Option<String>,
+
+    /// Also update every project in subdirectories, skipping ignored files,
+    /// node_modules, vendor and target
+    #[arg(long)]
+    recursive: bool,
+
+    /// Projects to update, and registry lookups to make, at once
+    /// [default: the number of CPUs]
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<NonZeroUsize>,
+
+    /// Only use registry metadata from the cache; never go online
+    #[arg(long)]
+    offline: bool,
+
+    /// Fetch all registry metadata again instead of using the cache
+    #[arg(long, conflicts_with = "offline")]
+    refresh: bool,
+
+    /// Look every version up in this snapshot instead of the registries, so
+    /// the update plan is the same wherever it is made
+    #[arg(long, value_name = "FILE")]
+    registry_snapshot: Option<PathBuf>,
+
+    /// Save the registry metadata this run used as a snapshot
+    #[arg(long, value_name = "FILE")]
+    save_snapshot: Option<PathBuf>,
+
+    /// Output format for the results [default: text]
+    #[arg(short, long, value_enum)]
+    format: Option<Format>,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
 }
 
-fn main() {
-    let cli = Cli::parse();
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// List or prune the backups in .depup/backups
+    Backups {
+        #[command(subcommand)]
+        action: BackupsAction,
+    },
+    /// Inspect or clear the registry metadata cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Restore the files an update changed from its backup
+    Revert {
+        /// Backup to restore, as shown by `depup backups list` [default: the most recent]
+        #[arg(long, value_name = "ID")]
+        run: Option<String>,
+        /// Only restore files of this ecosystem (`cargo`) or with this name
+        /// (`package.json`); may be repeated
+        #[arg(long, value_name = "ECOSYSTEM|FILE")]
+        only: Vec<String>,
+        /// Restore files even if they were edited after depup changed them
+        #[arg(long)]
+        force: bool,
+        /// Path to the project directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+}
 
-    // Set global config for utils
-    utils::set_verbose(cli.verbose);
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Print the effective configuration merged from every config file
+    Show {
+        /// Path to the project directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+}
 
-    utils::info("Starting dependencies update...");
+#[derive(Subcommand, Debug)]
+enum CacheAction {
+    /// Show how many entries are cached per ecosystem and how old they are
+    Stats,
+    /// Delete every cached entry
+    Clear,
+}
 
-    // Determine if we should create backups (default is true, unless --no-backup is specified)
-    let create_backups = !cli.no_backup;
+#[derive(Subcommand, Debug)]
+enum BackupsAction {
+    /// Show every backup, oldest first
+    List {
+        /// Path to the project directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+    /// Delete all but the most recent backups
+    Prune {
+        /// Backups to keep [default: keep-backups from the configuration, or 10]
+        #[arg(long, value_name = "N")]
+        keep: Option<usize>,
+        /// Path to the project directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+}
 
-    // If reverting, check if backups exist
-    if cli.revert {
-        if !utils::check_backups_exist(&cli.path) {
-            utils::error("No backup files found. Cannot revert changes.");
-            return;
+// Parse the command line, refusing update options next to a subcommand; the
+// logging options are global and go with either
+fn parse_cli() -> Cli {
+    let mut command = Cli::command();
+    let matches = command.get_matches_mut();
+    let update_option = command
+        .get_arguments()
+        .find(|arg| {
+            !arg.is_global_set()
+                && matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+        })
+        .map(ToString::to_string);
+    if let Some((name, _)) = matches.subcommand()
+        && let Some(option) = update_option
+    {
+        command
+            .error(
+                ErrorKind::ArgumentConflict,
+                format!("the subcommand '{}' cannot be used with '{}'", name, option),
+            )
+            .exit();
+    }
+    Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
+}
+
+fn main() -> ExitCode {
+    let cli = parse_cli();
+
+    // Messages go to stderr, leaving stdout to reports and diffs
+    let level = log::Level::from_flags(cli.quiet, cli.verbose);
+    if let Err(e) = log::init(level, cli.color, cli.log_file.as_deref()) {
+        log::error(&format!("Cannot open the log file: {}", e));
+        return ExitCode::FAILURE;
+    }
+
+    match &cli.command {
+        Some(Commands::Config {
+            action: ConfigAction::Show { path },
+        }) => return show_config(path),
+        Some(Commands::Backups {
+            action: BackupsAction::List { path },
+        }) => return list_backups(path),
+        Some(Commands::Backups {
+            action: BackupsAction::Prune { keep, path },
+        }) => return prune_backups(path, *keep),
+        Some(Commands::Cache {
+            action: CacheAction::Stats,
+        }) => return cache_stats(),
+        Some(Commands::Cache {
+            action: CacheAction::Clear,
+        }) => return clear_cache(),
+        Some(Commands::Revert {
+            run,
+            only,
+            force,
+            path,
+        }) => return revert(path, run.as_deref(), only, *force),
+        None => {}
+    }
+
+    let loaded = match config::load(&cli.path) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            log::error(&e.to_string());
+            return ExitCode::FAILURE;
         }
-        utils::info("Reverting changes using backup files...");
-        if let Err(e) = utils::revert_changes(&cli.path) {
-            utils::error(&format!("Failed to revert changes: {}", e));
-            return;
+    };
+    for source in &loaded.sources {
+        log::debug(&format!("Loaded configuration from {}", source.display()));
+    }
+    let mut config = loaded.config;
+
+    // Command line flags take precedence over the configuration
+    if let Some(level) = cli.level {
+        config.level = Some(level);
+    }
+    if let Some(days) = cli.cooldown {
+        config.cooldown = Some(days);
+        for ecosystem in config.ecosystems.values_mut() {
+            ecosystem.cooldown = None;
         }
-        utils::success("Changes reverted successfully.");
-        return;
     }
+    if let Some(channel) = cli.prerelease {
+        config.prerelease = Some(channel);
+        for ecosystem in config.ecosystems.values_mut() {
+            ecosystem.prerelease = None;
+        }
+    }
+
+    let format = cli.format.or(config.format).unwrap_or_default();
+
+    // Machine-readable formats print a single document and nothing else
+    let text = format == Format::Text;
+    if text {
+        log::info("Starting dependencies update...");
+    }
+
+    // Determine if we should create backups (default is true, unless --no-backup
+    // is specified or the configuration turns them off)
+    let create_backups = !cli.no_backup && config.backup.unwrap_or(true);
+
+    if cli.revert {
+        return revert(&cli.path, None, &[], false);
+    }
+
+    // --check never writes anything
+    let dry_run = cli.dry_run || cli.check;
 
-    // If backups are enabled, ensure *.backup is in .gitignore
-    if create_backups {
-        if let Err(e) = utils::ensure_backups_in_gitignore(&cli.path) {
-            utils::warning(&format!("Could not update .gitignore: {}", e));
+    // If backups are enabled, ensure .depup/ is in .gitignore
+    if create_backups
+        && !dry_run
+        && let Err(e) = utils::ensure_backups_in_gitignore(&cli.path)
+    {
+        log::warning(&format!("Could not update .gitignore: {}", e));
+    }
+
+    let snapshot = match cli.registry_snapshot.as_deref().map(Snapshot::load) {
+        Some(Ok(snapshot)) => Some(snapshot),
+        Some(Err(e)) => {
+            log::error(&e.to_string());
+            return ExitCode::FAILURE;
         }
+        None => None,
+    };
+
+    let keep_backups = config.keep_backups.unwrap_or(backup::DEFAULT_KEEP);
+    let options = Options {
+        backup: create_backups,
+        run_id: backup::run_id(),
+        backup_root: None,
+        recursive: cli.recursive,
+        jobs: cli.jobs.map_or_else(jobs::default_jobs, NonZeroUsize::get),
+        cache_dir: Cache::default_dir(),
+        cache: if cli.offline {
+            cache::Mode::Offline
+        } else if cli.refresh {
+            cache::Mode::Refresh
+        } else {
+            cache::Mode::Normal
+        },
+        // A replayed snapshot is asked about every dependency, not just what
+        // the package manager reports as outdated; offline runs find
+        // versions in the cache instead of asking the package manager's
+        // registry
+        resolve_from_registry: snapshot.is_some() || cli.offline,
+        // A replayed snapshot plans as it would have when it was taken
+        now: snapshot.as_ref().map_or_else(Utc::now, |s| s.created),
+        dry_run,
+        // Scanning formats report vulnerable and yanked versions too
+        audit: cli.check || matches!(format, Format::Sarif | Format::Junit),
+        config,
+    };
+    let http = HttpRegistry::new(&options.config);
+    let cached = CachedRegistry::new(
+        &http,
+        Cache::new(&options.cache_dir),
+        options.config.cache_ttl(),
+        options.cache,
+    );
+    let recording = Recording::new(match &snapshot {
+        Some(snapshot) => snapshot,
+        None => &cached,
+    });
+    let report = depup::run_with(&cli.path, &options, &recording);
+    match render::render(&report, format) {
+        Some(document) => println!("{}", document),
+        None => print_report(&report, cli.diff),
     }
 
-    let mut packages_found = false;
+    let mut failed = report.has_errors();
+    if let Some(path) = &cli.save_snapshot {
+        for e in recording.record_rest(&report, options.jobs) {
+            log::warning(&format!("Could not add to the snapshot: {}", e));
+        }
+        // Replays measure cooldowns from when the metadata was gathered
+        let saved = Snapshot {
+            created: options.now,
+            ..recording.into_snapshot()
+        };
+        match saved.save(path) {
+            Ok(()) if text => log::info(&format!(
+                "Saved the registry metadata of this run to {}",
+                path.display()
+            )),
+            Ok(()) => {}
+            Err(e) => {
+                log::error(&e.to_string());
+                failed = true;
+            }
+        }
+    }
 
-    // Change to the specified directory if needed
-    let original_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-    if cli.path != PathBuf::from(".") {
-        if let Err(e) = std::env::set_current_dir(&cli.path) {
-            utils::error(&format!(
-                "Failed to change to directory {}: {}",
-                cli.path.display(),
-                e
+    if let Some(id) = &report.backup {
+        if text && report.can_revert() {
+            log::info(&format!(
+                "Saved the original files as backup {}; run `depup revert` to undo.",
+                id
             ));
-            return;
         }
-        utils::debug(&format!(
-            "Changed working directory to {}",
-            cli.path.display()
-        ));
+        match Store::new(&cli.path).prune(keep_backups) {
+            Ok(removed) => {
+                for id in removed {
+                    log::debug(&format!("Removed old backup {}", id));
+                }
+            }
+            Err(e) => log::warning(&format!("Could not prune old backups: {}", e)),
+        }
     }
 
-    // Check for composer.json
-    if std::path::Path::new("composer.json").exists() {
-        if utils::is_command_available("composer") {
-            php::update_composer(create_backups);
-            packages_found = true;
-        } else {
-            utils::warning(
-                "composer.json found but composer is not installed. Skipping PHP dependencies.",
-            );
+    if cli.check {
+        if text {
+            print_summary(&report);
         }
+        return ExitCode::from(report.outcome().exit_code());
     }
 
-    // Check for package.json
-    if std::path::Path::new("package.json").exists() {
-        if utils::is_command_available("npm") {
-            node::update_npm(create_backups);
-            packages_found = true;
+    if text {
+        if report.packages_found() && report.dry_run {
+            log::success("Dry run completed. No files were changed.");
+        } else if report.packages_found() {
+            if !failed {
+                log::success("Dependency update completed.");
+            }
         } else {
-            utils::warning(
-                "package.json found but npm is not installed. Skipping Node.js dependencies.",
-            );
+            log::warning("No supported dependency files found or no package managers installed.");
         }
     }
 
-    // Check for Cargo.toml
-    if std::path::Path::new("Cargo.toml").exists() {
-        if utils::is_command_available("cargo") {
-            cargo::update_cargo(create_backups);
-            packages_found = true;
-        } else {
-            utils::warning(
-                "Cargo.toml found but cargo is not installed. Skipping Rust dependencies.",
-            );
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn show_config(path: &Path) -> ExitCode {
+    let loaded = match config::load(path) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            log::error(&e.to_string());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if loaded.sources.is_empty() {
+        println!("# No configuration files found; showing defaults");
+    } else {
+        println!("# Merged from, lowest precedence first:");
+        for source in &loaded.sources {
+            println!("#   {}", source.display());
         }
     }
 
-    // Change back to the original directory
-    if cli.path != PathBuf::from(".") {
-        if let Err(e) = std::env::set_current_dir(&original_dir) {
-            utils::warning(&format!(
-                "Failed to change back to original directory: {}",
-                e
-            ));
+    let names: Vec<&str> = ecosystem::registry().iter().map(|e| e.name()).collect();
+    print!("{}", loaded.config.with_defaults(&names).to_toml());
+    ExitCode::SUCCESS
+}
+
+fn list_backups(path: &Path) -> ExitCode {
+    let generations = match Store::new(path).list() {
+        Ok(generations) => generations,
+        Err(e) => {
+            log::error(&e.to_string());
+            return ExitCode::FAILURE;
         }
+    };
+
+    if generations.is_empty() {
+        log::info("No backups found.");
+    }
+    for generation in generations {
+        let files: Vec<String> = generation
+            .files
+            .iter()
+            .map(|file| file.path.display().to_string())
+            .collect();
+        println!(
+            "{}  {}  {}",
+            generation.id,
+            generation
+                .created
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            files.join(", ")
+        );
     }
+    ExitCode::SUCCESS
+}
+
+fn prune_backups(path: &Path, keep: Option<usize>) -> ExitCode {
+    let keep = match keep {
+        Some(keep) => keep,
+        None => match config::load(path) {
+            Ok(loaded) => loaded.config.keep_backups.unwrap_or(backup::DEFAULT_KEEP),
+            Err(e) => {
+                log::error(&e.to_string());
+                return ExitCode::FAILURE;
+            }
+        },
+    };
 
-    if packages_found {
-        utils::success("Dependency update completed.");
+    match Store::new(path).prune(keep) {
+        Ok(removed) => {
+            for id in &removed {
+                log::info(&format!("Removed backup {}", id));
+            }
+            log::success(&format!("Removed {} backup(s).", removed.len()));
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            log::error(&format!("Failed to prune backups: {}", e));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn cache_stats() -> ExitCode {
+    let cache = Cache::new(&Cache::default_dir());
+    let stats = match cache.stats() {
+        Ok(stats) => stats,
+        Err(e) => {
+            log::error(&e.to_string());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if stats.iter().all(|s| s.entries == 0) {
+        log::info(&format!("The cache in {} is empty.", cache.dir().display()));
+        return ExitCode::SUCCESS;
+    }
+    for ecosystem in &stats {
+        let oldest = ecosystem
+            .oldest
+            .map(|time| {
+                time.with_timezone(&Local)
+                    .format("oldest from %Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        println!(
+            "{}  {} entries  {:.1} KiB  {}",
+            ecosystem.ecosystem,
+            ecosystem.entries,
+            ecosystem.bytes as f64 / 1024.0,
+            oldest
+        );
+    }
+    log::info(&format!(
+        "{} entries in {}",
+        stats.iter().map(|s| s.entries).sum::<usize>(),
+        cache.dir().display()
+    ));
+    ExitCode::SUCCESS
+}
+
+fn clear_cache() -> ExitCode {
+    match Cache::new(&Cache::default_dir()).clear() {
+        Ok(removed) => {
+            log::success(&format!("Removed {} cached entries.", removed));
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            log::error(&format!("Failed to clear the cache: {}", e));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn revert(path: &Path, run: Option<&str>, only: &[String], force: bool) -> ExitCode {
+    let store = Store::new(path);
+    let generation = match run {
+        Some(id) => store.generation(id).map(Some),
+        None => store.latest(),
+    };
+    let generation = match generation {
+        Ok(Some(generation)) => generation,
+        Ok(None) => {
+            log::error("No backups found. Cannot revert changes.");
+            return ExitCode::FAILURE;
+        }
+        Err(e) => {
+            log::error(&e.to_string());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    log::info(&format!(
+        "Reverting changes using backup {}...",
+        generation.id
+    ));
+    let revert = match store.restore(&generation, only, force) {
+        Ok(revert) => revert,
+        Err(e) => {
+            log::error(&format!("Failed to revert changes: {}", e));
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if revert.is_empty() {
+        log::error(&format!(
+            "No files in backup {} match {}.",
+            generation.id,
+            only.join(", ")
+        ));
+        return ExitCode::FAILURE;
+    }
+    for path in &revert.restored {
+        log::info(&format!("Restored {}", path.display()));
+    }
+    for path in &revert.unchanged {
+        log::info(&format!("{} already matches the backup", path.display()));
+    }
+    for path in &revert.edited {
+        log::warning(&format!(
+            "{} was edited after depup changed it; left as is (use --force to restore it anyway)",
+            path.display()
+        ));
+    }
+
+    if revert.edited.is_empty() {
+        log::success(&format!(
+            "Changes reverted successfully: {} file(s) restored.",
+            revert.restored.len()
+        ));
+        ExitCode::SUCCESS
     } else {
-        utils::warning("No supported dependency files found or no package managers installed.");
+        log::warning(&format!(
+            "Reverted {} file(s); {} left as is.",
+            revert.restored.len(),
+            revert.edited.len()
+        ));
+        ExitCode::FAILURE
+    }
+}
+
+fn print_summary(report: &UpdateReport) {
+    let dependencies = || report.ecosystems.iter().flat_map(|e| &e.dependencies);
+    let outdated = dependencies().filter(|d| d.is_update()).count();
+    let insecure = dependencies().filter(|d| d.is_insecure()).count();
+
+    match report.outcome() {
+        Outcome::UpToDate => log::success("All dependencies are up to date."),
+        Outcome::Outdated => log::warning(&format!("{} outdated dependencies.", outdated)),
+        Outcome::Vulnerable => log::error(&format!(
+            "{} outdated dependencies, {} with security advisories or yanked versions.",
+            outdated, insecure
+        )),
+        Outcome::Failed => log::error("Check failed; results may be incomplete."),
+    }
+}
+
+fn print_report(report: &UpdateReport, show_diff: bool) {
+    // Group the output by project once there is more than one
+    let grouped = report
+        .ecosystems
+        .iter()
+        .any(|e| e.root != report.ecosystems[0].root);
+    let mut project = None;
+
+    for ecosystem in &report.ecosystems {
+        let manifest = ecosystem.manifest.display();
+        if grouped && project != Some(&ecosystem.root) {
+            project = Some(&ecosystem.root);
+            log::info(&format!("Project {}:", ecosystem.root.display()));
+        }
+
+        if let Some(reason) = &ecosystem.skipped {
+            log::warning(&format!(
+                "{} found but {}. Skipping {} dependencies.",
+                manifest, reason, ecosystem.ecosystem
+            ));
+            continue;
+        }
+
+        if report.dry_run {
+            log::info(&format!("Checking {} dependencies...", ecosystem.ecosystem));
+        } else {
+            log::info(&format!("Updating {} dependencies...", ecosystem.ecosystem));
+        }
+        for backup in &ecosystem.backups {
+            log::debug(&format!("Created backup: {}", backup.display()));
+        }
+        for command in &ecosystem.commands {
+            let status = match command.code {
+                Some(code) => format!("exit code {}", code),
+                None => "killed".to_string(),
+            };
+            log::debug(&format!(
+                "Ran {} in {:.1}s ({})",
+                command.command_line(),
+                command.duration.as_secs_f64(),
+                status
+            ));
+        }
+        for warning in &ecosystem.warnings {
+            log::warning(warning);
+        }
+
+        for dep in &ecosystem.dependencies {
+            if let Some(reason) = &dep.skip_reason
+                && *reason != SkipReason::UpToDate
+            {
+                log::info(&format!("Skipping {}: {}", dep.name, reason));
+            }
+        }
+
+        let updates: Vec<_> = ecosystem.updates().collect();
+        if updates.is_empty() && ecosystem.errors.is_empty() {
+            log::info(&format!(
+                "No outdated {} packages found.",
+                ecosystem.ecosystem
+            ));
+        }
+
+        let verb = if report.dry_run {
+            "Would update"
+        } else {
+            "Updating"
+        };
+        for dep in &updates {
+            let kind = dep
+                .update_kind
+                .map(|kind| format!(" ({})", kind))
+                .unwrap_or_default();
+            log::info(&format!(
+                "{} {} from {} to {}{}",
+                verb,
+                dep.name,
+                dep.from_version(),
+                dep.to_version(),
+                kind
+            ));
+        }
+
+        if ecosystem.manifest_updated {
+            log::info(&format!(
+                "Updated {} package(s) in {}",
+                updates.len(),
+                manifest
+            ));
+        } else if report.dry_run && !updates.is_empty() {
+            log::info(&format!(
+                "Would update {} package(s) in {}",
+                updates.len(),
+                manifest
+            ));
+        }
+
+        for dep in &ecosystem.dependencies {
+            for advisory in &dep.advisories {
+                log::warning(&format!(
+                    "{} has a security advisory {}: {}",
+                    dep.name, advisory.id, advisory.title
+                ));
+            }
+        }
+
+        if show_diff && let Some(change) = &ecosystem.change {
+            println!("{}", diff::colorize(&change.unified_diff()));
+        }
+
+        for error in &ecosystem.errors {
+            log::error(&error.to_string());
+        }
     }
 }
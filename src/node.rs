@@ -1,141 +1,202 @@
-use crate::utils;
+use crate::ecosystem::{
+    Candidate, Context, Dependency, Ecosystem, Edit, apply_json_edits, find_workspace,
+    json_dependencies, run_command, run_json,
+};
+use crate::error::Result;
+use crate::registry::{Release, parse_time};
+use crate::report::{Advisory, Audit};
+use crate::{log, version};
 use serde_json::Value;
-use std::fs::{copy, read_to_string, write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-pub fn update_npm(backup: bool) {
-    utils::info("Updating npm dependencies...");
+const SECTIONS: [&str; 2] = ["dependencies", "devDependencies"];
 
-    // Create backups first if enabled
-    if backup {
-        create_backups();
+/// Node.js projects managed with npm
+pub struct Npm;
+
+impl Ecosystem for Npm {
+    fn name(&self) -> &'static str {
+        "npm"
     }
 
-    // Read package.json
-    let content = match read_to_string("package.json") {
-        Ok(c) => c,
-        Err(_) => {
-            utils::error("Error reading package.json");
-            return;
-        }
-    };
+    fn command(&self) -> &'static str {
+        "npm"
+    }
 
-    let mut json: Value = match serde_json::from_str(&content) {
-        Ok(j) => j,
-        Err(_) => {
-            utils::error("Invalid package.json file");
-            return;
-        }
-    };
+    fn manifest(&self) -> &'static str {
+        "package.json"
+    }
 
-    // Get outdated packages
-    let outdated = get_outdated_packages();
-    if outdated.is_empty() {
-        utils::info("No outdated npm packages found.");
-        return;
-    }
-
-    let mut updates = 0;
-
-    // Update dependencies and devDependencies
-    for key in ["dependencies", "devDependencies"] {
-        if let Some(deps) = json.get_mut(key).and_then(|v| v.as_object_mut()) {
-            for (name, (current_version, latest_version)) in &outdated {
-                if deps.contains_key(name) {
-                    let new_ver = format!("^{}", latest_version);
-                    utils::info(&format!(
-                        "Updating {} from {} to {}",
-                        name, current_version, latest_version
-                    ));
-                    deps.insert(name.to_string(), Value::String(new_ver));
-                    updates += 1;
-                }
-            }
-        }
+    fn lockfile(&self) -> &'static str {
+        "package-lock.json"
     }
 
-    if updates > 0 {
-        // Write the updated package.json
-        if let Err(e) = write("package.json", serde_json::to_string_pretty(&json).unwrap()) {
-            utils::error(&format!("Failed to write updated package.json: {}", e));
-            return;
-        }
+    fn default_registry(&self) -> &'static str {
+        "https://registry.npmjs.org"
+    }
+
+    fn releases_url(&self, registry: &str, package: &str) -> Option<String> {
+        // Scoped packages keep the @ but escape the slash
+        Some(format!("{}/{}", registry, package.replace('/', "%2f")))
+    }
+
+    fn parse_releases(&self, _package: &str, body: &str) -> Vec<Release> {
+        serde_json::from_str(body)
+            .map(|json| parse_packument(&json))
+            .unwrap_or_default()
+    }
 
-        utils::info(&format!("Updated {} package(s) in package.json", updates));
+    // The nearest parent declaring `workspaces`, as npm looks for it
+    fn workspace_root(&self, root: &Path) -> Option<PathBuf> {
+        find_workspace(root, self.manifest(), |manifest| {
+            serde_json::from_str::<Value>(manifest)
+                .is_ok_and(|json| json.get("workspaces").is_some())
+        })
+    }
+
+    fn collect_dependencies(&self, manifest: &str) -> Result<Vec<Dependency>> {
+        json_dependencies(self, manifest, &SECTIONS)
+    }
+
+    fn resolve_candidates(
+        &self,
+        context: &Context,
+        dependencies: &[Dependency],
+    ) -> Result<Vec<Candidate>> {
+        Ok(get_outdated_packages(context)?
+            .into_iter()
+            .filter(|candidate| dependencies.iter().any(|dep| dep.name == candidate.name))
+            .collect())
+    }
 
-        // Run npm update to update the lock file
-        utils::info("Running npm update...");
+    fn apply(&self, manifest: &str, edits: &[Edit]) -> Result<String> {
+        apply_json_edits(self, manifest, edits)
+    }
+
+    fn audit(&self, context: &Context) -> Result<Audit> {
+        let mut cmd = Command::new("npm");
+        cmd.args(["audit", "--json"]);
+        with_registry(&mut cmd, context);
+        Ok(run_json(context, cmd)?
+            .map(|json| parse_audit(&json))
+            .unwrap_or_default())
+    }
+
+    fn refresh_lockfile(&self, context: &Context) -> Result<()> {
         let mut cmd = Command::new("npm");
         cmd.arg("update");
+        with_registry(&mut cmd, context);
+        if context.offline {
+            cmd.arg("--offline");
+        }
 
-        // Add --verbose flag if verbose mode is enabled
-        if utils::is_verbose() {
+        // Add --verbose flag when npm output is logged (-vv)
+        if log::level() >= log::Level::Trace {
             cmd.arg("--verbose");
         }
 
-        cmd.status().unwrap();
-    } else {
-        utils::info("No changes needed in package.json");
+        run_command(context, cmd)
     }
 }
 
-fn get_outdated_packages() -> Vec<(String, (String, String))> {
+/// Parse a registry packument: every version, with publish times in `time`
+/// and the dist-tags pointing at it
+pub fn parse_packument(json: &Value) -> Vec<Release> {
+    let times = json.get("time");
+    let dist_tags = json.get("dist-tags").and_then(|t| t.as_object());
+    json.get("versions")
+        .and_then(|v| v.as_object())
+        .into_iter()
+        .flatten()
+        .map(|(version, _)| Release {
+            published: times
+                .and_then(|t| t.get(version))
+                .and_then(|t| t.as_str())
+                .and_then(parse_time),
+            tags: dist_tags
+                .into_iter()
+                .flatten()
+                .filter(|(_, tagged)| tagged.as_str() == Some(version))
+                .map(|(tag, _)| tag.clone())
+                .collect(),
+            ..Release::new(version)
+        })
+        .collect()
+}
+
+// Point npm at the configured registry instead of the one in .npmrc
+fn with_registry(cmd: &mut Command, context: &Context) {
+    if let Some(registry) = &context.config.registry {
+        cmd.args(["--registry", registry]);
+    }
+}
+
+fn get_outdated_packages(context: &Context) -> Result<Vec<Candidate>> {
     let mut outdated = Vec::new();
 
-    let output = Command::new("npm").args(["outdated", "--json"]).output();
-
-    match output {
-        Ok(out) => {
-            if !out.stdout.is_empty() {
-                match serde_json::from_slice::<Value>(&out.stdout) {
-                    Ok(json) => {
-                        if let Some(obj) = json.as_object() {
-                            for (name, details) in obj {
-                                if let (Some(current), Some(latest)) = (
-                                    details.get("current").and_then(|c| c.as_str()),
-                                    details.get("latest").and_then(|l| l.as_str()),
-                                ) {
-                                    if current != latest {
-                                        outdated.push((
-                                            name.clone(),
-                                            (current.to_string(), latest.to_string()),
-                                        ));
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Err(_) => {
-                        utils::warning("Failed to parse npm outdated output");
-                    }
-                }
+    let mut cmd = Command::new("npm");
+    cmd.args(["outdated", "--json"]);
+    with_registry(&mut cmd, context);
+    let Some(json) = run_json(context, cmd)? else {
+        return Ok(outdated);
+    };
+
+    if let Some(obj) = json.as_object() {
+        for (name, details) in obj {
+            if let (Some(current), Some(latest)) = (
+                details.get("current").and_then(|c| c.as_str()),
+                details.get("latest").and_then(|l| l.as_str()),
+            ) && version::is_newer(latest, current)
+            {
+                outdated.push(Candidate {
+                    name: name.clone(),
+                    current: current.to_string(),
+                    latest: latest.to_string(),
+                });
             }
         }
-        Err(e) => {
-            utils::warning(&format!("Failed to run npm outdated: {}", e));
-        }
     }
 
-    outdated
+    Ok(outdated)
 }
 
-fn create_backups() {
-    // Create backup of package.json
-    if copy("package.json", "package.json.backup").is_ok() {
-        utils::debug("Created backup: package.json.backup");
-    } else {
-        utils::warning("Failed to create package.json backup");
-    }
+/// Parse `npm audit --json` (npm 7+): one entry per vulnerable package, whose
+/// `via` list mixes advisory objects with names of vulnerable dependencies
+pub fn parse_audit(json: &Value) -> Audit {
+    let mut advisories = Vec::new();
 
-    // Create backup of package-lock.json if it exists
-    if std::path::Path::new("package-lock.json").exists() {
-        if copy("package-lock.json", "package-lock.json.backup").is_ok() {
-            utils::debug("Created backup: package-lock.json.backup");
-        } else {
-            utils::warning("Failed to create package-lock.json backup");
+    let Some(vulnerabilities) = json.get("vulnerabilities").and_then(|v| v.as_object()) else {
+        return Audit::default();
+    };
+
+    for (name, details) in vulnerabilities {
+        let via = details.get("via").and_then(|v| v.as_array());
+        for source in via.into_iter().flatten().filter(|v| v.is_object()) {
+            let url = source.get("url").and_then(|u| u.as_str());
+            advisories.push(Advisory {
+                package: name.clone(),
+                id: url
+                    .and_then(|u| u.rsplit('/').next())
+                    .map(str::to_string)
+                    .or_else(|| source.get("source").map(|s| s.to_string()))
+                    .unwrap_or_default(),
+                title: source
+                    .get("title")
+                    .and_then(|t| t.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                severity: source
+                    .get("severity")
+                    .and_then(|s| s.as_str())
+                    .map(str::to_string),
+                url: url.map(str::to_string),
+            });
         }
-        utils::info("Created backups of npm files");
-    } else {
-        utils::info("Created backup of package.json");
+    }
+
+    Audit {
+        advisories,
+        yanked: Vec::new(),
     }
 }
@@ -1,158 +1,192 @@
-use crate::utils;
+use crate::ecosystem::{
+    Candidate, Context, Dependency, Ecosystem, Edit, apply_json_edits, json_dependencies,
+    run_command, run_json,
+};
+use crate::error::Result;
+use crate::registry::{Release, parse_time};
+use crate::report::{Advisory, Audit};
+use crate::{log, version};
 use serde_json::Value;
-use std::fs::{copy, read_to_string, write};
 use std::process::Command;
 
-pub fn update_composer(backup: bool) {
-    utils::info("Updating composer dependencies...");
+const SECTIONS: [&str; 2] = ["require", "require-dev"];
 
-    // Create backups first if enabled
-    if backup {
-        create_backups();
+/// PHP projects managed with Composer
+pub struct Composer;
+
+impl Ecosystem for Composer {
+    fn name(&self) -> &'static str {
+        "composer"
     }
 
-    // Read composer.json
-    let content = match read_to_string("composer.json") {
-        Ok(c) => c,
-        Err(_) => {
-            utils::error("Error reading composer.json");
-            return;
-        }
-    };
+    fn command(&self) -> &'static str {
+        "composer"
+    }
 
-    let mut json: Value = match serde_json::from_str(&content) {
-        Ok(j) => j,
-        Err(_) => {
-            utils::error("Invalid composer.json file");
-            return;
-        }
-    };
+    fn manifest(&self) -> &'static str {
+        "composer.json"
+    }
 
-    // Get outdated packages
-    let outdated = get_outdated_packages();
-    if outdated.is_empty() {
-        utils::info("No outdated composer packages found.");
-        return;
-    }
-
-    let mut updates = 0;
-
-    // Update both require and require-dev sections
-    for section_name in ["require", "require-dev"] {
-        if let Some(section) = json.get_mut(section_name).and_then(|s| s.as_object_mut()) {
-            for (name, (current_version, latest_version)) in &outdated {
-                if section.contains_key(name) {
-                    let new_ver = format!("^{}", latest_version);
-                    utils::info(&format!(
-                        "Updating {} from {} to {}",
-                        name, current_version, latest_version
-                    ));
-                    section.insert(name.to_string(), Value::String(new_ver));
-                    updates += 1;
-                }
-            }
-        }
+    fn lockfile(&self) -> &'static str {
+        "composer.lock"
     }
 
-    if updates > 0 {
-        // Write the updated composer.json
-        if let Err(e) = write(
-            "composer.json",
-            serde_json::to_string_pretty(&json).unwrap(),
-        ) {
-            utils::error(&format!("Failed to write updated composer.json: {}", e));
-            return;
-        }
+    fn default_registry(&self) -> &'static str {
+        "https://repo.packagist.org"
+    }
+
+    fn releases_url(&self, registry: &str, package: &str) -> Option<String> {
+        Some(format!("{}/p2/{}.json", registry, package))
+    }
+
+    fn parse_releases(&self, package: &str, body: &str) -> Vec<Release> {
+        serde_json::from_str(body)
+            .map(|json| parse_packagist(&json, package))
+            .unwrap_or_default()
+    }
+
+    fn is_package(&self, name: &str) -> bool {
+        // Platform requirements such as `php` have no vendor
+        name.contains('/')
+    }
+
+    fn collect_dependencies(&self, manifest: &str) -> Result<Vec<Dependency>> {
+        json_dependencies(self, manifest, &SECTIONS)
+    }
 
-        utils::info(&format!("Updated {} package(s) in composer.json", updates));
+    fn resolve_candidates(
+        &self,
+        context: &Context,
+        dependencies: &[Dependency],
+    ) -> Result<Vec<Candidate>> {
+        Ok(get_outdated_packages(context)?
+            .into_iter()
+            .filter(|candidate| dependencies.iter().any(|dep| dep.name == candidate.name))
+            .collect())
+    }
+
+    fn apply(&self, manifest: &str, edits: &[Edit]) -> Result<String> {
+        apply_json_edits(self, manifest, edits)
+    }
 
-        // Run composer update to update the lock file
-        utils::info("Running composer update...");
+    fn audit(&self, context: &Context) -> Result<Audit> {
+        let mut cmd = Command::new("composer");
+        cmd.args(["audit", "--format=json"]);
+        Ok(run_json(context, cmd)?
+            .map(|json| parse_audit(&json))
+            .unwrap_or_default())
+    }
 
+    fn refresh_lockfile(&self, context: &Context) -> Result<()> {
         let mut cmd = Command::new("composer");
         cmd.arg("update");
+        // Composer has no flag for this, only an environment variable
+        if context.offline {
+            cmd.env("COMPOSER_DISABLE_NETWORK", "1");
+        }
 
-        // Add -v flag if verbose mode is enabled
-        if utils::is_verbose() {
+        // Add -v flag when composer output is logged (-vv)
+        if log::level() >= log::Level::Trace {
             cmd.arg("-v");
         }
 
-        cmd.status().unwrap();
-    } else {
-        utils::info("No changes needed in composer.json");
+        run_command(context, cmd)
     }
 }
 
-fn get_outdated_packages() -> Vec<(String, (String, String))> {
+/// Parse Packagist's `p2` metadata, whose entries are minified: each one only
+/// lists the fields that differ from the entry before it
+pub fn parse_packagist(json: &Value, package: &str) -> Vec<Release> {
+    let entries = json
+        .pointer(&format!(
+            "/packages/{}",
+            package.replace('~', "~0").replace('/', "~1")
+        ))
+        .and_then(|p| p.as_array());
+
+    let mut time: Option<&str> = None;
+    let mut releases = Vec::new();
+    for entry in entries.into_iter().flatten() {
+        match entry.get("time").and_then(|t| t.as_str()) {
+            Some("__unset") => time = None,
+            Some(t) => time = Some(t),
+            None => {}
+        }
+        if let Some(version) = entry.get("version").and_then(|v| v.as_str()) {
+            releases.push(Release {
+                published: time.and_then(parse_time),
+                ..Release::new(version)
+            });
+        }
+    }
+    releases
+}
+
+fn get_outdated_packages(context: &Context) -> Result<Vec<Candidate>> {
     let mut outdated = Vec::new();
 
-    let output = Command::new("composer")
-        .args(["outdated", "-D", "--format=json"])
-        .output();
-
-    match output {
-        Ok(out) => {
-            if !out.stdout.is_empty() {
-                match serde_json::from_slice::<Value>(&out.stdout) {
-                    Ok(json) => {
-                        if let Some(installed) = json.get("installed").and_then(|i| i.as_array()) {
-                            for package in installed {
-                                let name =
-                                    package.get("name").and_then(|n| n.as_str()).unwrap_or("");
-                                let current = package
-                                    .get("version")
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or("");
-                                let latest =
-                                    package.get("latest").and_then(|l| l.as_str()).unwrap_or("");
-                                let status = package
-                                    .get("latest-status")
-                                    .and_then(|s| s.as_str())
-                                    .unwrap_or("");
-
-                                if status != "up-to-date"
-                                    && !current.is_empty()
-                                    && !latest.is_empty()
-                                {
-                                    outdated.push((
-                                        name.to_string(),
-                                        (current.to_string(), latest.to_string()),
-                                    ));
-                                }
-                            }
-                        }
-                    }
-                    Err(_) => {
-                        utils::warning("Failed to parse composer outdated output");
-                    }
-                }
+    let mut cmd = Command::new("composer");
+    cmd.args(["outdated", "-D", "--format=json"]);
+    let Some(json) = run_json(context, cmd)? else {
+        return Ok(outdated);
+    };
+
+    if let Some(installed) = json.get("installed").and_then(|i| i.as_array()) {
+        for package in installed {
+            let name = package.get("name").and_then(|n| n.as_str()).unwrap_or("");
+            let current = package
+                .get("version")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let latest = package.get("latest").and_then(|l| l.as_str()).unwrap_or("");
+            let status = package
+                .get("latest-status")
+                .and_then(|s| s.as_str())
+                .unwrap_or("");
+
+            if status != "up-to-date"
+                && !current.is_empty()
+                && !latest.is_empty()
+                && version::is_newer(latest, current)
+            {
+                outdated.push(Candidate {
+                    name: name.to_string(),
+                    current: current.to_string(),
+                    latest: latest.to_string(),
+                });
             }
         }
-        Err(e) => {
-            utils::warning(&format!("Failed to run composer outdated: {}", e));
-        }
     }
 
-    outdated
+    Ok(outdated)
 }
 
-fn create_backups() {
-    // Create backup of composer.json
-    if copy("composer.json", "composer.json.backup").is_ok() {
-        utils::debug("Created backup: composer.json.backup");
-    } else {
-        utils::warning("Failed to create composer.json backup");
-    }
+/// Parse `composer audit --format=json`; `advisories` is an empty array rather
+/// than an object when nothing was found
+pub fn parse_audit(json: &Value) -> Audit {
+    let mut advisories = Vec::new();
 
-    // Create backup of composer.lock if it exists
-    if std::path::Path::new("composer.lock").exists() {
-        if copy("composer.lock", "composer.lock.backup").is_ok() {
-            utils::debug("Created backup: composer.lock.backup");
-        } else {
-            utils::warning("Failed to create composer.lock backup");
+    let Some(packages) = json.get("advisories").and_then(|a| a.as_object()) else {
+        return Audit::default();
+    };
+
+    for (name, entries) in packages {
+        for entry in entries.as_array().into_iter().flatten() {
+            let field = |key: &str| entry.get(key).and_then(|v| v.as_str()).map(str::to_string);
+            advisories.push(Advisory {
+                package: name.clone(),
+                id: field("cve")
+                    .or_else(|| field("advisoryId"))
+                    .unwrap_or_default(),
+                title: field("title").unwrap_or_default(),
+                severity: field("severity"),
+                url: field("link"),
+            });
         }
-        utils::info("Created backups of composer files");
-    } else {
-        utils::info("Created backup of composer.json");
+    }
+
+    Audit {
+        advisories,
+        yanked: Vec::new(),
     }
 }
@@ -0,0 +1,162 @@
+use crate::config::Config;
+use crate::ecosystem::Ecosystem;
+use crate::error::{Error, Result};
+use crate::{log, version};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use ureq::Agent;
+use ureq::tls::{RootCerts, TlsConfig};
+
+/// A published version of a package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Release {
+    pub version: String,
+    /// When the version was published, if the registry records it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published: Option<DateTime<Utc>>,
+    /// Withdrawn from the registry, e.g. a yanked crate
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub yanked: bool,
+    /// Distribution tags pointing at the version, e.g. npm's `next`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl Release {
+    pub fn new(version: &str) -> Release {
+        Release {
+            version: version.to_string(),
+            published: None,
+            yanked: false,
+            tags: Vec::new(),
+        }
+    }
+}
+
+/// A source of release metadata for packages.
+pub trait Registry: Sync {
+    /// Every published version of a package, in no particular order
+    fn releases(&self, ecosystem: &dyn Ecosystem, package: &str) -> Result<Vec<Release>>;
+
+    /// Where an ecosystem's packages are looked up, e.g. its registry URL
+    fn location(&self, ecosystem: &dyn Ecosystem) -> String {
+        ecosystem.default_registry().to_string()
+    }
+}
+
+/// Looks up releases in each ecosystem's public registry, or the registry
+/// configured for it.
+pub struct HttpRegistry {
+    agent: Agent,
+    /// Configured registry URLs, keyed by ecosystem name
+    urls: BTreeMap<String, String>,
+}
+
+impl HttpRegistry {
+    pub fn new(config: &Config) -> HttpRegistry {
+        // Use the system trust store so registries behind corporate proxies work
+        let agent = Agent::config_builder()
+            .tls_config(
+                TlsConfig::builder()
+                    .root_certs(RootCerts::PlatformVerifier)
+                    .build(),
+            )
+            .timeout_global(Some(Duration::from_secs(30)))
+            .user_agent(concat!("depup/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .into();
+
+        let urls = config
+            .ecosystems
+            .iter()
+            .filter_map(|(name, ecosystem)| Some((name.clone(), ecosystem.registry.clone()?)))
+            .collect();
+
+        HttpRegistry { agent, urls }
+    }
+}
+
+impl Registry for HttpRegistry {
+    fn releases(&self, ecosystem: &dyn Ecosystem, package: &str) -> Result<Vec<Release>> {
+        let location = self.location(ecosystem);
+        let Some(url) = ecosystem.releases_url(&location, package) else {
+            return Err(Error::Registry {
+                url: location,
+                message: format!(
+                    "{} is not a valid {} package name",
+                    package,
+                    ecosystem.name()
+                ),
+            });
+        };
+
+        log::trace(&format!("Fetching {}", url));
+        let body = self
+            .agent
+            .get(&url)
+            .call()
+            .and_then(|mut response| response.body_mut().read_to_string())
+            .map_err(|e| Error::Registry {
+                url: url.clone(),
+                message: e.to_string(),
+            })?;
+
+        Ok(ecosystem.parse_releases(package, &body))
+    }
+
+    fn location(&self, ecosystem: &dyn Ecosystem) -> String {
+        self.urls
+            .get(ecosystem.name())
+            .map(String::as_str)
+            .unwrap_or(ecosystem.default_registry())
+            .trim_end_matches('/')
+            .to_string()
+    }
+}
+
+/// The newest release above `current` that is not yanked and passes `allowed`
+///
+/// Versions are ordered by semver, so `1.0.0-rc.2` sorts after
+/// `1.0.0-beta.11` and before `1.0.0`; whether pre-releases are acceptable at
+/// all is up to `allowed`.
+pub fn newest(
+    releases: &[Release],
+    current: &str,
+    allowed: impl Fn(&Release) -> bool,
+) -> Option<String> {
+    let current = version::parse(current)?;
+    releases
+        .iter()
+        .filter(|release| !release.yanked)
+        .filter_map(|release| Some((version::parse(&release.version)?, release)))
+        .filter(|(version, _)| *version > current)
+        .filter(|(_, release)| allowed(release))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, release)| release.version.clone())
+}
+
+/// The release a registry would call the latest: the one npm's `latest`
+/// dist-tag points at, or else the newest stable version that is not yanked
+pub fn latest(releases: &[Release]) -> Option<&Release> {
+    releases
+        .iter()
+        .find(|release| release.tags.iter().any(|tag| tag == "latest"))
+        .or_else(|| {
+            releases
+                .iter()
+                .filter(|release| !release.yanked)
+                .filter_map(|release| Some((version::parse(&release.version)?, release)))
+                .filter(|(version, _)| version.pre.is_empty())
+                .max_by(|(a, _), (b, _)| a.cmp(b))
+                .map(|(_, release)| release)
+        })
+}
+
+/// Parse a registry timestamp such as `2024-01-02T03:04:05Z`
+pub(crate) fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
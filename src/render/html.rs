@@ -0,0 +1,73 @@
+use super::escape_xml as escape;
+use crate::report::UpdateReport;
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: left; }
+th { background: #f3f3f3; }
+.major { color: #b00020; }
+.minor { color: #a15c00; }
+.patch { color: #1b7a1b; }
+.error { color: #b00020; }";
+
+/// Render the planned or applied updates as a standalone HTML page
+pub fn render(report: &UpdateReport) -> String {
+    let mut body = String::new();
+
+    for ecosystem in &report.ecosystems {
+        body.push_str(&format!("<h2>{}</h2>\n", escape(&ecosystem.title())));
+
+        if let Some(reason) = &ecosystem.skipped {
+            body.push_str(&format!(
+                "<p>Skipped: {}.</p>\n",
+                escape(&reason.to_string())
+            ));
+            continue;
+        }
+
+        let updates: Vec<_> = ecosystem.updates().collect();
+        if updates.is_empty() {
+            body.push_str("<p>No updates.</p>\n");
+        } else {
+            body.push_str("<table>\n<tr><th>Package</th><th>From</th><th>To</th><th>Kind</th><th>Section</th></tr>\n");
+            for dep in updates {
+                let kind = dep.update_kind.map(|k| k.to_string()).unwrap_or_default();
+                body.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"{}\">{}</td><td>{}</td></tr>\n",
+                    escape(&dep.name),
+                    escape(dep.from_version()),
+                    escape(dep.to_version()),
+                    kind,
+                    kind,
+                    escape(&dep.section),
+                ));
+            }
+            body.push_str("</table>\n");
+        }
+
+        for error in &ecosystem.errors {
+            body.push_str(&format!(
+                "<p class=\"error\">{}</p>\n",
+                escape(&error.to_string())
+            ));
+        }
+    }
+
+    format!(
+        "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<title>Dependency updates</title>
+<style>
+{}
+</style>
+</head>
+<body>
+<h1>Dependency updates</h1>
+{}</body>
+</html>
+",
+        STYLE, body
+    )
+}
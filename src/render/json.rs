@@ -0,0 +1,40 @@
+use crate::report::{EcosystemReport, Outcome, UpdateReport};
+use serde::Serialize;
+
+/// Version of the JSON document layout. Bumped whenever a field is renamed
+/// or removed; new fields may be added without a bump.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Document<'a> {
+    schema_version: u32,
+    tool: Tool,
+    dry_run: bool,
+    /// Backup generation to pass to `depup revert --run`
+    backup: Option<&'a str>,
+    outcome: Outcome,
+    ecosystems: &'a [EcosystemReport],
+}
+
+#[derive(Serialize)]
+struct Tool {
+    name: &'static str,
+    version: &'static str,
+}
+
+/// Render the report as a pretty-printed JSON document
+pub fn render(report: &UpdateReport) -> String {
+    let document = Document {
+        schema_version: SCHEMA_VERSION,
+        tool: Tool {
+            name: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+        },
+        dry_run: report.dry_run,
+        backup: report.backup.as_deref(),
+        outcome: report.outcome(),
+        ecosystems: &report.ecosystems,
+    };
+
+    serde_json::to_string_pretty(&document).expect("report serialization cannot fail")
+}
@@ -0,0 +1,98 @@
+use super::{escape_xml, findings};
+use crate::report::{EcosystemReport, UpdateReport};
+use std::path::Path;
+
+/// Render the report as JUnit XML: one test suite per ecosystem and one test
+/// case per dependency, failing when the dependency is outdated, yanked or
+/// vulnerable
+pub fn render(report: &UpdateReport) -> String {
+    let mut suites = String::new();
+    let (mut total_tests, mut total_failures, mut total_errors) = (0, 0, 0);
+
+    for ecosystem in &report.ecosystems {
+        let mut cases = String::new();
+        let (mut tests, mut failures, mut errors, mut skipped) = (0, 0, 0, 0);
+
+        if let Some(reason) = &ecosystem.skipped {
+            tests += 1;
+            skipped += 1;
+            cases.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\">\n      <skipped message=\"{}\"/>\n    </testcase>\n",
+                escape_xml(&ecosystem.manifest.to_string_lossy()),
+                escape_xml(&classname(ecosystem, None)),
+                escape_xml(&reason.to_string()),
+            ));
+        }
+
+        for dep in &ecosystem.dependencies {
+            tests += 1;
+            let classname = classname(ecosystem, Some(&dep.section));
+            let found = findings(dep);
+            if found.is_empty() {
+                cases.push_str(&format!(
+                    "    <testcase name=\"{}\" classname=\"{}\"/>\n",
+                    escape_xml(&dep.name),
+                    escape_xml(&classname),
+                ));
+                continue;
+            }
+
+            failures += 1;
+            cases.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\">\n",
+                escape_xml(&dep.name),
+                escape_xml(&classname),
+            ));
+            for finding in found {
+                cases.push_str(&format!(
+                    "      <failure type=\"{}\" message=\"{}\"/>\n",
+                    finding.rule.id(),
+                    escape_xml(&finding.message),
+                ));
+            }
+            cases.push_str("    </testcase>\n");
+        }
+
+        for error in &ecosystem.errors {
+            tests += 1;
+            errors += 1;
+            cases.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\">\n      <error type=\"{}\" message=\"{}\"/>\n    </testcase>\n",
+                escape_xml(&ecosystem.manifest.to_string_lossy()),
+                escape_xml(&classname(ecosystem, None)),
+                error.kind(),
+                escape_xml(&error.to_string()),
+            ));
+        }
+
+        suites.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\">\n{}  </testsuite>\n",
+            escape_xml(&ecosystem.title()),
+            tests,
+            failures,
+            errors,
+            skipped,
+            cases,
+        ));
+        total_tests += tests;
+        total_failures += failures;
+        total_errors += errors;
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"depup\" tests=\"{}\" failures=\"{}\" errors=\"{}\">\n{}</testsuites>\n",
+        total_tests, total_failures, total_errors, suites
+    )
+}
+
+// Dotted class name of an ecosystem's test cases, led by its project when
+// that is not where depup ran, e.g. `crates/a.cargo.dependencies`
+fn classname(ecosystem: &EcosystemReport, section: Option<&str>) -> String {
+    let mut parts = Vec::new();
+    if ecosystem.root != Path::new(".") {
+        parts.push(ecosystem.root.to_string_lossy().into_owned());
+    }
+    parts.push(ecosystem.ecosystem.clone());
+    parts.extend(section.map(str::to_string));
+    parts.join(".")
+}
@@ -0,0 +1,47 @@
+use crate::report::UpdateReport;
+
+/// Render the planned or applied updates as Markdown, one table per ecosystem
+pub fn render(report: &UpdateReport) -> String {
+    let mut out = String::from("## Dependency updates\n");
+
+    for ecosystem in &report.ecosystems {
+        out.push_str(&format!("\n### {}\n\n", ecosystem.title()));
+
+        if let Some(reason) = &ecosystem.skipped {
+            out.push_str(&format!("Skipped: {}.\n", reason));
+            continue;
+        }
+
+        let updates: Vec<_> = ecosystem.updates().collect();
+        if updates.is_empty() {
+            out.push_str("No updates.\n");
+        } else {
+            out.push_str("| Package | Update | Kind | Section |\n");
+            out.push_str("|---------|--------|------|---------|\n");
+            for dep in updates {
+                out.push_str(&format!(
+                    "| {} | `{}` → `{}` | {} | {} |\n",
+                    escape(&dep.name),
+                    escape(dep.from_version()),
+                    escape(dep.to_version()),
+                    dep.update_kind.map(|k| k.to_string()).unwrap_or_default(),
+                    escape(&dep.section),
+                ));
+            }
+        }
+
+        if !ecosystem.errors.is_empty() {
+            out.push('\n');
+            for error in &ecosystem.errors {
+                out.push_str(&format!("- **Error:** {}\n", escape(&error.to_string())));
+            }
+        }
+    }
+
+    out
+}
+
+// Keep cell contents from breaking the table
+fn escape(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
@@ -0,0 +1,142 @@
+//! Renderings of an [`UpdateReport`] for machines and pull requests.
+
+pub mod html;
+pub mod json;
+pub mod junit;
+pub mod markdown;
+pub mod sarif;
+
+use crate::report::{DependencyReport, UpdateReport};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Output formats supported by `--format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Human-readable log lines
+    #[default]
+    Text,
+    /// A single JSON document
+    Json,
+    /// Markdown tables, e.g. for a pull request body
+    Markdown,
+    /// A standalone HTML page
+    Html,
+    /// A SARIF 2.1.0 log for code scanning
+    Sarif,
+    /// JUnit XML with a test case per dependency
+    Junit,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("no variant is skipped");
+        f.write_str(value.get_name())
+    }
+}
+
+/// Render a report as a document in the given format
+///
+/// Returns `None` for [`Format::Text`], which the CLI prints as it goes.
+pub fn render(report: &UpdateReport, format: Format) -> Option<String> {
+    match format {
+        Format::Text => None,
+        Format::Json => Some(json::render(report)),
+        Format::Markdown => Some(markdown::render(report)),
+        Format::Html => Some(html::render(report)),
+        Format::Sarif => Some(sarif::render(report)),
+        Format::Junit => Some(junit::render(report)),
+    }
+}
+
+/// A problem with a dependency, as reported by SARIF and JUnit output.
+pub(crate) struct Finding {
+    pub rule: Rule,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Rule {
+    Vulnerable,
+    Yanked,
+    Outdated,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 3] = [Rule::Vulnerable, Rule::Yanked, Rule::Outdated];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Rule::Vulnerable => "vulnerable-dependency",
+            Rule::Yanked => "yanked-dependency",
+            Rule::Outdated => "outdated-dependency",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Rule::Vulnerable => "Dependency has a known security advisory",
+            Rule::Yanked => "Dependency version has been yanked",
+            Rule::Outdated => "Dependency has a newer version available",
+        }
+    }
+
+    /// SARIF level
+    pub fn level(self) -> &'static str {
+        match self {
+            Rule::Vulnerable | Rule::Yanked => "error",
+            Rule::Outdated => "warning",
+        }
+    }
+}
+
+/// Everything wrong with a dependency, most severe first
+pub(crate) fn findings(dep: &DependencyReport) -> Vec<Finding> {
+    let mut found: Vec<Finding> = dep
+        .advisories
+        .iter()
+        .map(|advisory| Finding {
+            rule: Rule::Vulnerable,
+            message: format!(
+                "{} is affected by {}: {}",
+                dep.name, advisory.id, advisory.title
+            ),
+        })
+        .collect();
+
+    if dep.yanked {
+        found.push(Finding {
+            rule: Rule::Yanked,
+            message: format!("{} {} has been yanked", dep.name, dep.from_version()),
+        });
+    }
+
+    if dep.is_update() {
+        let kind = dep
+            .update_kind
+            .map(|k| format!(" ({})", k))
+            .unwrap_or_default();
+        found.push(Finding {
+            rule: Rule::Outdated,
+            message: format!(
+                "{} {} can be updated to {}{}",
+                dep.name,
+                dep.from_version(),
+                dep.to_version(),
+                kind
+            ),
+        });
+    }
+
+    found
+}
+
+/// Escape text for XML and HTML content and attributes
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
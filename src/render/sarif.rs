@@ -0,0 +1,61 @@
+use super::{Rule, findings};
+use crate::report::UpdateReport;
+use serde_json::{Value, json};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Render outdated, yanked and vulnerable dependencies as a SARIF 2.1.0 log,
+/// with each result pointing at the dependency's line in its manifest
+pub fn render(report: &UpdateReport) -> String {
+    let rules: Vec<Value> = Rule::ALL
+        .iter()
+        .map(|rule| {
+            json!({
+                "id": rule.id(),
+                "shortDescription": { "text": rule.description() },
+                "defaultConfiguration": { "level": rule.level() },
+            })
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    for ecosystem in &report.ecosystems {
+        for dep in &ecosystem.dependencies {
+            for finding in findings(dep) {
+                let mut location = json!({
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": ecosystem.manifest.to_string_lossy() },
+                    }
+                });
+                if let Some(line) = dep.line {
+                    location["physicalLocation"]["region"] = json!({ "startLine": line });
+                }
+
+                results.push(json!({
+                    "ruleId": finding.rule.id(),
+                    "level": finding.rule.level(),
+                    "message": { "text": finding.message },
+                    "locations": [location],
+                }));
+            }
+        }
+    }
+
+    let log = json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    });
+
+    serde_json::to_string_pretty(&log).expect("SARIF serialization cannot fail")
+}
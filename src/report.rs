@@ -0,0 +1,337 @@
+use crate::diff;
+use crate::ecosystem::Ecosystem;
+use crate::error::Error;
+use crate::runner::CommandOutput;
+use crate::version::{Level, UpdateKind};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The outcome of a depup run across every detected ecosystem.
+#[derive(Debug, Default, Serialize)]
+pub struct UpdateReport {
+    /// Nothing was written and no package manager was run
+    pub dry_run: bool,
+    /// Backup generation holding the original files, if any were changed
+    pub backup: Option<String>,
+    pub ecosystems: Vec<EcosystemReport>,
+}
+
+impl UpdateReport {
+    /// Whether any ecosystem was actually processed
+    pub fn packages_found(&self) -> bool {
+        self.ecosystems.iter().any(|e| e.skipped.is_none())
+    }
+
+    /// Whether any ecosystem recorded an error
+    pub fn has_errors(&self) -> bool {
+        self.ecosystems.iter().any(|e| !e.errors.is_empty())
+    }
+
+    /// Whether the backup generation holds files this run changed and kept,
+    /// leaving `depup revert` something to undo
+    pub fn can_revert(&self) -> bool {
+        self.backup.is_some()
+            && self
+                .ecosystems
+                .iter()
+                .any(|e| !e.backups.is_empty() && e.manifest_updated)
+    }
+
+    /// Whether a detected ecosystem went unchecked because its package
+    /// manager is missing
+    pub fn has_unchecked(&self) -> bool {
+        self.ecosystems
+            .iter()
+            .any(|e| matches!(e.skipped, Some(SkipReason::NotInstalled { .. })))
+    }
+
+    /// Summarize the run for `--check`; errors and unchecked ecosystems take
+    /// precedence over advisories and yanked versions, which take precedence
+    /// over plain updates
+    pub fn outcome(&self) -> Outcome {
+        let dependencies = || self.ecosystems.iter().flat_map(|e| &e.dependencies);
+
+        if self.has_errors() || self.has_unchecked() {
+            Outcome::Failed
+        } else if dependencies().any(DependencyReport::is_insecure) {
+            Outcome::Vulnerable
+        } else if dependencies().any(|d| d.is_update()) {
+            Outcome::Outdated
+        } else {
+            Outcome::UpToDate
+        }
+    }
+}
+
+/// Overall result of a run, as used for CI exit codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    UpToDate,
+    /// Newer versions are available
+    Outdated,
+    /// A dependency has a known security advisory or a yanked version
+    Vulnerable,
+    /// Something went wrong, so the result cannot be trusted
+    Failed,
+}
+
+impl Outcome {
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Outcome::UpToDate => 0,
+            Outcome::Failed => 1,
+            Outcome::Outdated => 3,
+            Outcome::Vulnerable => 4,
+        }
+    }
+}
+
+/// The outcome of updating a single ecosystem.
+#[derive(Debug, Serialize)]
+pub struct EcosystemReport {
+    /// Ecosystem identifier, e.g. `cargo`
+    pub ecosystem: String,
+    /// Project directory relative to where depup ran, `.` for that directory
+    pub root: PathBuf,
+    /// Manifest path relative to where depup ran
+    pub manifest: PathBuf,
+    /// Set when the ecosystem was detected but not processed
+    pub skipped: Option<SkipReason>,
+    /// Copies of the files in the backup generation, made before updating
+    pub backups: Vec<PathBuf>,
+    pub dependencies: Vec<DependencyReport>,
+    /// The manifest rewrite, whether or not it was written to disk
+    #[serde(skip)]
+    pub change: Option<ManifestChange>,
+    pub manifest_updated: bool,
+    pub lockfile_refreshed: bool,
+    /// The manifest and lockfile were put back after the lockfile refresh, a
+    /// post-update command or a verification command failed
+    pub rolled_back: bool,
+    /// Every package manager, post-update and verification command that ran
+    pub commands: Vec<CommandOutput>,
+    pub warnings: Vec<String>,
+    pub errors: Vec<Error>,
+}
+
+impl EcosystemReport {
+    pub fn new(ecosystem: &dyn Ecosystem) -> Self {
+        EcosystemReport {
+            ecosystem: ecosystem.name().to_string(),
+            root: PathBuf::from("."),
+            manifest: PathBuf::from(ecosystem.manifest()),
+            skipped: None,
+            backups: Vec::new(),
+            dependencies: Vec::new(),
+            change: None,
+            manifest_updated: false,
+            lockfile_refreshed: false,
+            rolled_back: false,
+            commands: Vec::new(),
+            warnings: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Place the report in `project`, a directory relative to where depup
+    /// ran, so its paths stay apart from those of other projects
+    pub fn relocate(&mut self, project: &Path) {
+        if project.as_os_str().is_empty() {
+            return;
+        }
+        self.manifest = project.join(&self.manifest);
+        if let Some(change) = &mut self.change {
+            change.path = project.join(&change.path);
+        }
+        self.root = project.to_path_buf();
+    }
+
+    /// The ecosystem's name, followed by its project unless that is where
+    /// depup ran, e.g. `cargo (crates/a)`
+    pub fn title(&self) -> String {
+        if self.root == Path::new(".") {
+            self.ecosystem.clone()
+        } else {
+            format!("{} ({})", self.ecosystem, self.root.display())
+        }
+    }
+
+    /// Dependencies whose requirement is rewritten, or would be in a dry run
+    pub fn updates(&self) -> impl Iterator<Item = &DependencyReport> {
+        self.dependencies.iter().filter(|d| d.is_update())
+    }
+}
+
+/// A manifest's contents before and after the planned edits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestChange {
+    pub path: PathBuf,
+    pub before: String,
+    pub after: String,
+}
+
+impl ManifestChange {
+    /// Unified diff of the change
+    pub fn unified_diff(&self) -> String {
+        diff::unified(&self.path, &self.before, &self.after)
+    }
+}
+
+/// What happened to one dependency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencyReport {
+    pub name: String,
+    /// Manifest section, e.g. `devDependencies`
+    pub section: String,
+    pub old_requirement: String,
+    pub new_requirement: Option<String>,
+    /// Version currently in use, as reported by the package manager
+    pub current_version: Option<String>,
+    /// Newest version found
+    pub latest_version: Option<String>,
+    /// Version the requirement moves to; older than `latest_version` when
+    /// the update policy rules out the newest
+    pub target_version: Option<String>,
+    /// Size of the update from the current to the target version
+    pub update_kind: Option<UpdateKind>,
+    pub skip_reason: Option<SkipReason>,
+    /// Security advisories affecting the version in use
+    pub advisories: Vec<Advisory>,
+    /// The version in use has been yanked from the registry
+    pub yanked: bool,
+    /// 1-based line of the declaration in the manifest, when known
+    pub line: Option<usize>,
+}
+
+impl DependencyReport {
+    pub fn is_update(&self) -> bool {
+        self.new_requirement.is_some() && self.skip_reason.is_none()
+    }
+
+    /// Whether the version in use has a security advisory or was yanked
+    pub fn is_insecure(&self) -> bool {
+        !self.advisories.is_empty() || self.yanked
+    }
+
+    /// Version being moved away from, falling back to the old requirement
+    pub fn from_version(&self) -> &str {
+        self.current_version
+            .as_deref()
+            .unwrap_or(&self.old_requirement)
+    }
+
+    /// Version being moved to, falling back to the new requirement
+    pub fn to_version(&self) -> &str {
+        self.target_version
+            .as_deref()
+            .or(self.latest_version.as_deref())
+            .or(self.new_requirement.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+/// Findings from a package manager's audit command.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Audit {
+    pub advisories: Vec<Advisory>,
+    /// Packages whose installed version has been yanked
+    pub yanked: Vec<String>,
+}
+
+/// A known vulnerability reported by the package manager's audit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Advisory {
+    pub package: String,
+    /// Advisory identifier, e.g. `GHSA-...`, `CVE-...` or `RUSTSEC-...`
+    pub id: String,
+    pub title: String,
+    pub severity: Option<String>,
+    pub url: Option<String>,
+}
+
+/// Why a dependency or ecosystem was left alone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SkipReason {
+    /// No newer version was found
+    UpToDate,
+    /// The package manager binary is not installed
+    NotInstalled { command: String },
+    /// The ecosystem is turned off in the configuration
+    Disabled,
+    /// The package is on an ignore list or matches an ignore rule
+    Ignored { reason: Option<String> },
+    /// The newest version is outside the range a rule allows
+    Pinned {
+        allow: String,
+        reason: Option<String>,
+    },
+    /// A rule skips updates of this kind
+    UpdateKindIgnored {
+        update: UpdateKind,
+        reason: Option<String>,
+    },
+    /// The newest version is a bigger jump than the configured level allows
+    LevelExceeded { update: UpdateKind, level: Level },
+    /// The newest version is a pre-release outside the allowed channel
+    Prerelease { version: String },
+    /// Every newer version was published within the cooldown period
+    Cooldown {
+        version: String,
+        days: u32,
+        /// When the newest version was published, if it could be looked up
+        published: Option<DateTime<Utc>>,
+    },
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::UpToDate => write!(f, "up to date"),
+            SkipReason::NotInstalled { command } => write!(f, "{} is not installed", command),
+            SkipReason::Disabled => write!(f, "disabled in the configuration"),
+            SkipReason::Ignored { reason } => {
+                write!(f, "ignored")?;
+                write_reason(f, reason)
+            }
+            SkipReason::Pinned { allow, reason } => {
+                write!(f, "pinned to {}", allow)?;
+                write_reason(f, reason)
+            }
+            SkipReason::UpdateKindIgnored { update, reason } => {
+                write!(f, "{} updates are ignored", update)?;
+                write_reason(f, reason)
+            }
+            SkipReason::LevelExceeded { update, level } => {
+                write!(f, "{} update exceeds the {} level", update, level)
+            }
+            SkipReason::Prerelease { version } => write!(f, "{} is a pre-release", version),
+            SkipReason::Cooldown {
+                version,
+                days,
+                published: Some(published),
+            } => write!(
+                f,
+                "{} was published on {}, within the {}-day cooldown",
+                version,
+                published.format("%Y-%m-%d"),
+                days
+            ),
+            SkipReason::Cooldown { version, days, .. } => write!(
+                f,
+                "could not check the release date of {} for the {}-day cooldown",
+                version, days
+            ),
+        }
+    }
+}
+
+fn write_reason(f: &mut fmt::Formatter<'_>, reason: &Option<String>) -> fmt::Result {
+    match reason {
+        Some(reason) => write!(f, " ({})", reason),
+        None => Ok(()),
+    }
+}
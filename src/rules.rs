@@ -0,0 +1,180 @@
+use crate::report::SkipReason;
+use crate::version::{self, Level, UpdateKind};
+use chrono::NaiveDate;
+use globset::Glob;
+use serde::{Deserialize, Serialize};
+use toml::value::Datetime;
+
+/// A policy for the packages matching a glob, from `[[rules]]` in the
+/// configuration.
+///
+/// A rule without `allow`, `ignore-updates` or `level` ignores its packages
+/// entirely.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Rule {
+    /// Package name or glob, e.g. `@types/*`
+    pub package: String,
+    /// Only apply to this ecosystem, e.g. `npm`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ecosystem: Option<String>,
+    /// Version range new versions must fall within, e.g. `<19`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow: Option<String>,
+    /// Kinds of update to skip, e.g. `["major"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore_updates: Vec<UpdateKind>,
+    /// Update level for these packages, overriding the global one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<Level>,
+    /// Last day the rule applies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<Datetime>,
+    /// Why the rule exists, shown in reports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl Rule {
+    /// A rule ignoring every package matching the pattern
+    pub fn ignore(package: &str) -> Rule {
+        Rule {
+            package: package.to_string(),
+            ecosystem: None,
+            allow: None,
+            ignore_updates: Vec::new(),
+            level: None,
+            expires: None,
+            reason: None,
+        }
+    }
+
+    /// A rule restricting new versions of matching packages to a range
+    pub fn pin(package: &str, allow: &str) -> Rule {
+        Rule {
+            allow: Some(allow.to_string()),
+            ..Rule::ignore(package)
+        }
+    }
+
+    /// Check the package pattern, version range and expiry date
+    pub fn validate(&self) -> Result<(), String> {
+        Glob::new(&self.package)
+            .map_err(|e| format!("invalid package pattern `{}`: {}", self.package, e))?;
+        if let Some(allow) = &self.allow
+            && version::parse_range(allow).is_none()
+        {
+            return Err(format!(
+                "invalid version range `{}` for `{}`",
+                allow, self.package
+            ));
+        }
+        if self.expires.is_some() && self.expiry_date().is_none() {
+            return Err(format!("`expires` for `{}` must be a date", self.package));
+        }
+        Ok(())
+    }
+
+    /// Whether the rule covers a package of the given ecosystem
+    pub fn matches(&self, ecosystem: &str, package: &str) -> bool {
+        self.ecosystem.as_deref().is_none_or(|e| e == ecosystem)
+            && Glob::new(&self.package).is_ok_and(|glob| glob.compile_matcher().is_match(package))
+    }
+
+    /// The day after which the rule no longer applies
+    pub fn expiry_date(&self) -> Option<NaiveDate> {
+        let date = self.expires?.date?;
+        NaiveDate::from_ymd_opt(date.year.into(), date.month.into(), date.day.into())
+    }
+
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
+        self.expiry_date().is_some_and(|expires| today > expires)
+    }
+
+    /// Why the rule blocks an update from `current` to `latest`, if it does
+    ///
+    /// Without a known update only whole-package ignores apply. The rule's
+    /// `level` is applied by the planner in place of the global level.
+    pub fn check(&self, update: Option<(&str, &str)>) -> Option<SkipReason> {
+        let reason = self.reason.clone();
+        if self.allow.is_none() && self.ignore_updates.is_empty() && self.level.is_none() {
+            return Some(SkipReason::Ignored { reason });
+        }
+
+        let (current, latest) = update?;
+        if let Some(allow) = &self.allow
+            && version::satisfies(latest, allow) == Some(false)
+        {
+            return Some(SkipReason::Pinned {
+                allow: allow.clone(),
+                reason,
+            });
+        }
+
+        let kind = version::classify(current, latest)?;
+        self.ignore_updates
+            .contains(&kind)
+            .then_some(SkipReason::UpdateKindIgnored {
+                update: kind,
+                reason,
+            })
+    }
+}
+
+/// Marker that starts an inline annotation in a manifest comment
+pub const ANNOTATION: &str = "depup:";
+
+/// Find the directive of an inline annotation such as `# depup: pin <2`
+pub fn annotation(comment: &str) -> Option<&str> {
+    let start = comment.find(ANNOTATION)? + ANNOTATION.len();
+    Some(comment[start..].trim())
+}
+
+/// Build the rule for an inline annotation's directive
+///
+/// Supported directives are `ignore`, `ignore <kind>...` (e.g. `ignore major`)
+/// and `pin <range>`.
+pub fn parse_annotation(package: &str, directive: &str) -> Result<Rule, String> {
+    let (command, argument) = directive
+        .split_once(char::is_whitespace)
+        .map(|(command, argument)| (command, argument.trim()))
+        .unwrap_or((directive, ""));
+
+    let rule = match command {
+        "ignore" => Rule {
+            ignore_updates: argument
+                .split_whitespace()
+                .map(str::parse::<UpdateKind>)
+                .collect::<Result<_, _>>()?,
+            ..Rule::ignore(package)
+        },
+        "pin" if !argument.is_empty() => Rule::pin(package, argument),
+        "pin" => return Err("`pin` needs a version range".to_string()),
+        _ => return Err(format!("unknown depup annotation `{}`", directive)),
+    };
+
+    rule.validate()?;
+    Ok(rule)
+}
+
+/// The level set by the first active rule for a package that has one
+pub fn level(rules: &[Rule], ecosystem: &str, package: &str, today: NaiveDate) -> Option<Level> {
+    rules
+        .iter()
+        .filter(|rule| rule.matches(ecosystem, package) && !rule.is_expired(today))
+        .find_map(|rule| rule.level)
+}
+
+/// The first reason any active rule gives to skip updating a package
+pub fn evaluate(
+    rules: &[Rule],
+    ecosystem: &str,
+    package: &str,
+    update: Option<(&str, &str)>,
+    today: NaiveDate,
+) -> Option<SkipReason> {
+    rules
+        .iter()
+        .filter(|rule| rule.matches(ecosystem, package) && !rule.is_expired(today))
+        .find_map(|rule| rule.check(update))
+}
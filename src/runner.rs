@@ -0,0 +1,253 @@
+use crate::error::{Error, Result};
+use serde::Serialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use wait_timeout::ChildExt;
+
+/// Seconds a package manager may run when no timeout is configured
+pub const DEFAULT_TIMEOUT: u64 = 600;
+
+// How long output of a killed command may take to arrive
+const PIPE_GRACE: Duration = Duration::from_millis(200);
+
+/// A finished command and everything it printed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommandOutput {
+    /// Program followed by its arguments
+    pub argv: Vec<String>,
+    /// Directory the command ran in, if not depup's own
+    pub dir: Option<PathBuf>,
+    /// Exit code; `None` if the process was killed or could not be started
+    pub code: Option<i32>,
+    /// Left out of reports, where it would dwarf everything else
+    #[serde(skip)]
+    pub stdout: String,
+    pub stderr: String,
+    #[serde(serialize_with = "serialize_millis", rename = "duration_ms")]
+    pub duration: Duration,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    /// The program and arguments as one line, for messages
+    pub fn command_line(&self) -> String {
+        self.argv.join(" ")
+    }
+}
+
+/// Runs the package managers and configured commands.
+///
+/// Swapped for a scripted fake in tests.
+pub trait CommandRunner: Sync {
+    /// Run a command to completion, killing it once `timeout` has passed
+    ///
+    /// A non-zero exit is not an error here; only failing to start the
+    /// command or running out of time is.
+    fn run(&self, command: &mut Command, timeout: Option<Duration>) -> Result<CommandOutput>;
+}
+
+/// Runs commands as child processes.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, command: &mut Command, timeout: Option<Duration>) -> Result<CommandOutput> {
+        let argv = argv(command);
+        let started = Instant::now();
+        // A process group of its own lets a timeout end everything it started
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(command, 0);
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::Command {
+                command: argv.join(" "),
+                message: e.to_string(),
+            })?;
+
+        // Drain both pipes while waiting so a chatty command cannot block
+        let stdout = Drain::start(child.stdout.take());
+        let stderr = Drain::start(child.stderr.take());
+        let status = wait(&mut child, timeout).map_err(|e| Error::Command {
+            command: argv.join(" "),
+            message: e.to_string(),
+        })?;
+        let Some(status) = status else {
+            // Processes the command started may still hold the pipes open,
+            // so take what has arrived rather than waiting for the end
+            let output = CommandOutput {
+                argv,
+                dir: command.get_current_dir().map(Path::to_path_buf),
+                code: None,
+                stdout: stdout.partial(),
+                stderr: stderr.partial(),
+                duration: started.elapsed(),
+            };
+            return Err(Error::Timeout {
+                command: output.command_line(),
+                seconds: timeout.unwrap_or_default().as_secs(),
+                output: Box::new(output),
+            });
+        };
+
+        Ok(CommandOutput {
+            argv,
+            dir: command.get_current_dir().map(Path::to_path_buf),
+            code: status.code(),
+            stdout: stdout.finish(),
+            stderr: stderr.finish(),
+            duration: started.elapsed(),
+        })
+    }
+}
+
+/// Passes commands on to another runner and keeps their output for the
+/// report.
+pub struct Recorder<'a> {
+    inner: &'a dyn CommandRunner,
+    outputs: Mutex<Vec<CommandOutput>>,
+}
+
+impl<'a> Recorder<'a> {
+    pub fn new(inner: &'a dyn CommandRunner) -> Recorder<'a> {
+        Recorder {
+            inner,
+            outputs: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Every command that ran or was attempted, in the order they ran
+    pub fn into_outputs(self) -> Vec<CommandOutput> {
+        self.outputs.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl CommandRunner for Recorder<'_> {
+    fn run(&self, command: &mut Command, timeout: Option<Duration>) -> Result<CommandOutput> {
+        let started = Instant::now();
+        let result = self.inner.run(command, timeout);
+        let output = match &result {
+            Ok(output) => output.clone(),
+            Err(Error::Timeout { output, .. }) => output.as_ref().clone(),
+            // Commands that could not be started are listed without output
+            Err(_) => CommandOutput {
+                argv: argv(command),
+                dir: command.get_current_dir().map(Path::to_path_buf),
+                code: None,
+                stdout: String::new(),
+                stderr: String::new(),
+                duration: started.elapsed(),
+            },
+        };
+        self.outputs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(output);
+        result
+    }
+}
+
+/// The program and arguments of a command
+pub fn argv(command: &Command) -> Vec<String> {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect()
+}
+
+// Reads a pipe on a thread of its own, keeping what has arrived so far
+struct Drain {
+    bytes: Arc<Mutex<Vec<u8>>>,
+    reader: thread::JoinHandle<()>,
+}
+
+impl Drain {
+    fn start(pipe: Option<impl Read + Send + 'static>) -> Drain {
+        let bytes = Arc::new(Mutex::new(Vec::new()));
+        let shared = Arc::clone(&bytes);
+        let reader = thread::spawn(move || {
+            let Some(mut pipe) = pipe else {
+                return;
+            };
+            let mut chunk = [0; 8192];
+            while let Ok(read @ 1..) = pipe.read(&mut chunk) {
+                shared
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .extend_from_slice(&chunk[..read]);
+            }
+        });
+        Drain { bytes, reader }
+    }
+
+    // Everything, once the pipe has closed
+    fn finish(self) -> String {
+        let _ = self.reader.join();
+        text(&self.bytes)
+    }
+
+    // What has arrived, giving the pipe a moment to close first
+    fn partial(self) -> String {
+        let deadline = Instant::now() + PIPE_GRACE;
+        while !self.reader.is_finished() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        text(&self.bytes)
+    }
+}
+
+fn text(bytes: &Mutex<Vec<u8>>) -> String {
+    String::from_utf8_lossy(&bytes.lock().unwrap_or_else(|e| e.into_inner())).into_owned()
+}
+
+// Wait for the child, killing it when the timeout passes; `None` on timeout
+fn wait(
+    child: &mut Child,
+    timeout: Option<Duration>,
+) -> std::io::Result<Option<std::process::ExitStatus>> {
+    let Some(timeout) = timeout else {
+        return child.wait().map(Some);
+    };
+    match child.wait_timeout(timeout)? {
+        Some(status) => Ok(Some(status)),
+        None => {
+            kill(child)?;
+            child.wait()?;
+            Ok(None)
+        }
+    }
+}
+
+// Kill the child and whatever it started in its process group, such as the
+// commands a shell runs
+#[cfg(unix)]
+fn kill(child: &mut Child) -> std::io::Result<()> {
+    let group = libc::pid_t::try_from(child.id()).map_err(std::io::Error::other)?;
+    // SAFETY: kill only sends a signal and touches no memory of ours
+    if unsafe { libc::kill(-group, libc::SIGKILL) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+fn kill(child: &mut Child) -> std::io::Result<()> {
+    child.kill()
+}
+
+fn serialize_millis<S: serde::Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_u128(duration.as_millis())
+}
@@ -0,0 +1,170 @@
+use crate::ecosystem::{self, Ecosystem};
+use crate::error::{Error, Result};
+use crate::registry::{Registry, Release};
+use crate::report::UpdateReport;
+use crate::{jobs, utils, version};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Version of the snapshot format, bumped on incompatible changes
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Registry metadata frozen in a file, so runs can be repeated exactly.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub snapshot_version: u32,
+    pub created: DateTime<Utc>,
+    /// Releases of each package, keyed by ecosystem and package name
+    pub ecosystems: BTreeMap<String, BTreeMap<String, Vec<Release>>>,
+}
+
+impl Default for Snapshot {
+    fn default() -> Self {
+        Snapshot {
+            snapshot_version: SNAPSHOT_VERSION,
+            created: Utc::now(),
+            ecosystems: BTreeMap::new(),
+        }
+    }
+}
+
+impl Snapshot {
+    pub fn load(path: &Path) -> Result<Snapshot> {
+        let contents = fs::read_to_string(path).map_err(|source| Error::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let parse_error = |message: String| Error::Parse {
+            path: path.to_path_buf(),
+            message,
+        };
+        let snapshot: Snapshot =
+            serde_json::from_str(&contents).map_err(|e| parse_error(e.to_string()))?;
+        if snapshot.snapshot_version != SNAPSHOT_VERSION {
+            return Err(parse_error(format!(
+                "unsupported snapshot version {}",
+                snapshot.snapshot_version
+            )));
+        }
+        Ok(snapshot)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self).expect("snapshots serialize");
+        utils::write_atomic(path, format!("{}\n", contents).as_bytes()).map_err(|source| {
+            Error::Write {
+                path: path.to_path_buf(),
+                source,
+            }
+        })
+    }
+
+    /// Add the releases of a package, replacing any recorded before
+    pub fn insert(&mut self, ecosystem: &str, package: &str, releases: Vec<Release>) {
+        self.ecosystems
+            .entry(ecosystem.to_string())
+            .or_default()
+            .insert(package.to_string(), releases);
+    }
+}
+
+/// Answers lookups from the snapshot alone; packages it lacks are errors.
+impl Registry for Snapshot {
+    fn releases(&self, ecosystem: &dyn Ecosystem, package: &str) -> Result<Vec<Release>> {
+        self.ecosystems
+            .get(ecosystem.name())
+            .and_then(|packages| packages.get(package))
+            .cloned()
+            .ok_or_else(|| Error::Snapshot {
+                ecosystem: ecosystem.name().to_string(),
+                package: package.to_string(),
+            })
+    }
+}
+
+/// Passes lookups on to another registry and keeps what it returns, to be
+/// saved as a snapshot.
+pub struct Recording<'a> {
+    inner: &'a dyn Registry,
+    snapshot: Mutex<Snapshot>,
+}
+
+impl<'a> Recording<'a> {
+    pub fn new(inner: &'a dyn Registry) -> Recording<'a> {
+        Recording {
+            inner,
+            snapshot: Mutex::new(Snapshot::default()),
+        }
+    }
+
+    /// Look up the dependencies in `report` that the run itself had no
+    /// need to, so a replay, which looks every one up, finds them all.
+    /// Returns the lookups that failed.
+    pub fn record_rest(&self, report: &UpdateReport, jobs: usize) -> Vec<Error> {
+        let ecosystems = ecosystem::registry();
+        let mut lookups: Vec<(&dyn Ecosystem, &str)> = Vec::new();
+        for ecosystem_report in &report.ecosystems {
+            let Some(ecosystem) = ecosystems
+                .iter()
+                .find(|e| e.name() == ecosystem_report.ecosystem)
+            else {
+                continue;
+            };
+            for dep in &ecosystem_report.dependencies {
+                // The same dependencies a replay looks up
+                if version::parse(&dep.old_requirement).is_some()
+                    && ecosystem.is_package(&dep.name)
+                    && !self.has(ecosystem.name(), &dep.name)
+                    && !lookups
+                        .iter()
+                        .any(|(e, name)| e.name() == ecosystem.name() && *name == dep.name)
+                {
+                    lookups.push((ecosystem.as_ref(), &dep.name));
+                }
+            }
+        }
+
+        jobs::map(&lookups, jobs, |&(ecosystem, package)| {
+            self.releases(ecosystem, package)
+        })
+        .into_iter()
+        .filter_map(Result::err)
+        .collect()
+    }
+
+    // Whether a package has been recorded already
+    fn has(&self, ecosystem: &str, package: &str) -> bool {
+        self.snapshot
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .ecosystems
+            .get(ecosystem)
+            .is_some_and(|packages| packages.contains_key(package))
+    }
+
+    /// Every successful lookup so far
+    pub fn into_snapshot(self) -> Snapshot {
+        self.snapshot
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Registry for Recording<'_> {
+    fn releases(&self, ecosystem: &dyn Ecosystem, package: &str) -> Result<Vec<Release>> {
+        let releases = self.inner.releases(ecosystem, package)?;
+        self.snapshot
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(ecosystem.name(), package, releases.clone());
+        Ok(releases)
+    }
+
+    fn location(&self, ecosystem: &dyn Ecosystem) -> String {
+        self.inner.location(ecosystem)
+    }
+}
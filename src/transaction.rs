@@ -0,0 +1,57 @@
+use crate::error::{Error, Result};
+use crate::utils;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The contents of the files an update touches, taken before it starts, so
+/// they can all be put back if it fails part way.
+#[derive(Debug)]
+pub struct Transaction {
+    /// Each file with its original contents, `None` if it did not exist
+    files: Vec<(PathBuf, Option<Vec<u8>>)>,
+}
+
+impl Transaction {
+    /// Snapshot the given files; missing ones are removed again on rollback
+    pub fn begin(paths: &[&Path]) -> Result<Transaction> {
+        let mut files = Vec::new();
+        for path in paths {
+            let contents = match fs::read(path) {
+                Ok(contents) => Some(contents),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(source) => {
+                    return Err(Error::Read {
+                        path: path.to_path_buf(),
+                        source,
+                    });
+                }
+            };
+            files.push((path.to_path_buf(), contents));
+        }
+        Ok(Transaction { files })
+    }
+
+    /// Write a file atomically as part of the transaction
+    pub fn write(&self, path: &Path, contents: &str) -> Result<()> {
+        utils::write_atomic(path, contents.as_bytes()).map_err(|source| Error::Write {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Put every file back as it was when the transaction began
+    pub fn rollback(self) -> Result<()> {
+        for (path, contents) in self.files {
+            let result = match contents {
+                Some(contents) => utils::write_atomic(&path, &contents),
+                None => fs::remove_file(&path).or_else(|e| match e.kind() {
+                    io::ErrorKind::NotFound => Ok(()),
+                    _ => Err(e),
+                }),
+            };
+            result.map_err(|source| Error::Write { path, source })?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+use crate::backup;
+use crate::cache::{self, Cache, CachedRegistry};
+use crate::config::Config;
+use crate::discover;
+use crate::ecosystem::{self, Ecosystem};
+use crate::jobs;
+use crate::registry::{HttpRegistry, Registry};
+use crate::report::{EcosystemReport, SkipReason, UpdateReport};
+use crate::runner::SystemRunner;
+use crate::utils;
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+
+/// Settings for a depup run.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Copy manifests and lockfiles to `.depup/backups` before changing them
+    pub backup: bool,
+    /// Backup generation the copies are recorded in
+    pub run_id: String,
+    /// Directory whose `.depup/backups` holds the copies; the project
+    /// directory itself if unset
+    pub backup_root: Option<PathBuf>,
+    /// Update every project found below the directory, not just the
+    /// directory itself
+    pub recursive: bool,
+    /// Projects updated, and registry lookups made, at once
+    pub jobs: usize,
+    /// Directory holding cached registry metadata
+    pub cache_dir: PathBuf,
+    /// Whether registry metadata may come from the cache or the network
+    pub cache: cache::Mode,
+    /// Find newer versions by looking every dependency up in the registry
+    /// instead of asking the package manager, as runs from a snapshot do
+    pub resolve_from_registry: bool,
+    /// Moment cooldowns and rule expiry dates are measured from; the time a
+    /// snapshot was taken when planning from one
+    pub now: DateTime<Utc>,
+    /// Plan updates without writing files or running package managers
+    pub dry_run: bool,
+    /// Look up security advisories with the package managers' audit commands
+    pub audit: bool,
+    /// Ignore lists, update level and per-ecosystem settings
+    pub config: Config,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            backup: true,
+            run_id: backup::run_id(),
+            backup_root: None,
+            recursive: false,
+            jobs: jobs::default_jobs(),
+            cache_dir: Cache::default_dir(),
+            cache: cache::Mode::default(),
+            resolve_from_registry: false,
+            now: Utc::now(),
+            dry_run: false,
+            audit: false,
+            config: Config::default(),
+        }
+    }
+}
+
+/// Update every ecosystem detected in the project at `root`, or in every
+/// project below it when [`Options::recursive`] is set
+///
+/// Versions are looked up in the configured registries, through the cache.
+/// Backups of all projects go to one generation in `root`.
+pub fn run(root: &Path, options: &Options) -> UpdateReport {
+    let http = HttpRegistry::new(&options.config);
+    let registry = CachedRegistry::new(
+        &http,
+        Cache::new(&options.cache_dir),
+        options.config.cache_ttl(),
+        options.cache,
+    );
+    run_with(root, options, &registry)
+}
+
+/// Like [`run`], but looking versions up in `registry`, e.g. a snapshot
+pub fn run_with(root: &Path, options: &Options, registry: &dyn Registry) -> UpdateReport {
+    let mut report = UpdateReport {
+        dry_run: options.dry_run,
+        ..UpdateReport::default()
+    };
+    let options = Options {
+        backup_root: Some(root.to_path_buf()),
+        ..options.clone()
+    };
+
+    let projects = if options.recursive {
+        discover::projects(root)
+    } else {
+        vec![root.to_path_buf()]
+    };
+    let ecosystems = ecosystem::registry();
+    let units: Vec<(&Path, &dyn Ecosystem)> = projects
+        .iter()
+        .flat_map(|project| {
+            ecosystems
+                .iter()
+                .filter(|ecosystem| ecosystem.detect(project))
+                .map(move |ecosystem| (project.as_path(), ecosystem.as_ref()))
+        })
+        .collect();
+
+    // Each ecosystem of each project is a job of its own
+    report.ecosystems = jobs::map(&units, options.jobs, |&(project, ecosystem)| {
+        let mut ecosystem_report = update_ecosystem(ecosystem, project, &options, registry);
+        ecosystem_report.relocate(project.strip_prefix(root).unwrap_or(project));
+        ecosystem_report
+    });
+
+    if report.ecosystems.iter().any(|e| !e.backups.is_empty()) {
+        report.backup = Some(options.run_id.clone());
+    }
+
+    report
+}
+
+// Update one ecosystem of a project, unless it is disabled or its package
+// manager is missing
+fn update_ecosystem(
+    ecosystem: &dyn Ecosystem,
+    project: &Path,
+    options: &Options,
+    registry: &dyn Registry,
+) -> EcosystemReport {
+    if !options.config.is_enabled(ecosystem.name()) {
+        let mut skipped = EcosystemReport::new(ecosystem);
+        skipped.skipped = Some(SkipReason::Disabled);
+        return skipped;
+    }
+
+    // Planning from the registry alone needs no package manager
+    let from_registry = options.resolve_from_registry || !ecosystem.lists_outdated();
+    let needs_command = !options.dry_run || options.audit || !from_registry;
+    if needs_command && !utils::is_command_available(ecosystem.command()) {
+        let mut skipped = EcosystemReport::new(ecosystem);
+        skipped.skipped = Some(SkipReason::NotInstalled {
+            command: ecosystem.command().to_string(),
+        });
+        return skipped;
+    }
+
+    ecosystem::update(ecosystem, project, options, registry, &SystemRunner)
+}
@@ -1,42 +1,10 @@
-use colored::*;
+use crate::log;
+use std::fs::{self, File};
+use std::io::Write;
 use std::path::Path;
 use std::process::Command;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::fs;
 
-static VERBOSE: AtomicBool = AtomicBool::new(false);
-
-pub fn set_verbose(verbose: bool) {
-    VERBOSE.store(verbose, Ordering::Relaxed);
-}
-
-pub fn is_verbose() -> bool {
-    VERBOSE.load(Ordering::Relaxed)
-}
-
-pub fn info(message: &str) {
-    println!("{} {}", "[INFO]".blue(), message);
-}
-
-pub fn success(message: &str) {
-    println!("{} {}", "[SUCCESS]".green(), message);
-}
-
-pub fn error(message: &str) {
-    println!("{} {}", "[ERROR]".red(), message);
-}
-
-pub fn warning(message: &str) {
-    println!("{} {}", "[WARNING]".yellow(), message);
-}
-
-pub fn debug(message: &str) {
-    if is_verbose() {
-        println!("{} {}", "[DEBUG]".purple(), message);
-    }
-}
-
-// Add all *.backup files to .gitignore if in a git repository
+// Add the .depup/ directory holding backups to .gitignore if in a git repository
 pub fn ensure_backups_in_gitignore(project_path: &Path) -> Result<(), std::io::Error> {
     // Check if we're in a git repository
     let git_dir = project_path.join(".git");
@@ -46,21 +14,21 @@ pub fn ensure_backups_in_gitignore(project_path: &Path) -> Result<(), std::io::E
     }
 
     let gitignore_path = project_path.join(".gitignore");
-    let backup_pattern = "*.backup";
+    let backup_pattern = ".depup/";
 
     // Create .gitignore if it doesn't exist
     if !gitignore_path.exists() {
         std::fs::write(&gitignore_path, "")?;
-        debug("Created .gitignore file");
+        log::debug("Created .gitignore file");
     }
 
     // Read contents of .gitignore
     let contents = std::fs::read_to_string(&gitignore_path)?;
     let lines: Vec<&str> = contents.lines().collect();
 
-    // Check if *.backup pattern is already in .gitignore
+    // Check if the .depup/ pattern is already in .gitignore
     if !lines.contains(&backup_pattern) {
-        // Add *.backup to .gitignore
+        // Add .depup/ to .gitignore
         let mut new_content = contents;
         if !new_content.is_empty() && !new_content.ends_with('\n') {
             new_content.push('\n');
@@ -68,12 +36,47 @@ pub fn ensure_backups_in_gitignore(project_path: &Path) -> Result<(), std::io::E
         new_content.push_str(backup_pattern);
         new_content.push('\n');
         std::fs::write(&gitignore_path, new_content)?;
-        debug("Added *.backup to .gitignore");
+        log::debug("Added .depup/ to .gitignore");
     }
 
     Ok(())
 }
 
+// Write a file through a temporary file next to it that is renamed over the
+// original, so an interrupted run never leaves a half-written file behind
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), std::io::Error> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let Some(name) = path.file_name() else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} is not a file", path.display()),
+        ));
+    };
+    let temp = dir.join(format!(
+        ".{}.depup-{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let result = (|| {
+        let mut file = File::create(&temp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        // Keep the original's permissions
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp, metadata.permissions())?;
+        }
+        fs::rename(&temp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
 // Check if a command is available in the system
 pub fn is_command_available(command: &str) -> bool {
     let status = if cfg!(target_os = "windows") {
@@ -90,41 +93,3 @@ pub fn is_command_available(command: &str) -> bool {
 
     matches!(status, Ok(true))
 }
-
-// Check if backup files exist
-pub fn check_backups_exist(project_path: &Path) -> bool {
-    
-    // Look for any .backup files in the project directory
-    if let Ok(entries) = fs::read_dir(project_path) {
-        for entry in entries.flatten() {
-            if let Some(file_name) = entry.file_name().to_str() {
-                if file_name.ends_with(".backup") {
-                    return true;
-                }
-            }
-        }
-    }
-    
-    false
-}
-
-// Revert changes using backup files
-pub fn revert_changes(project_path: &Path) -> Result<(), std::io::Error> {
-    // Look for any .backup files in the project directory
-    if let Ok(entries) = fs::read_dir(project_path) {
-        for entry in entries.flatten() {
-            if let Some(file_name) = entry.file_name().to_str() {
-                if file_name.ends_with(".backup") {
-                    let original_file_name = file_name.trim_end_matches(".backup");
-                    let original_file_path = project_path.join(original_file_name);
-                    let backup_file_path = project_path.join(file_name);
-
-                    // Restore the original file from the backup
-                    fs::rename(backup_file_path, original_file_path)?;
-                }
-            }
-        }
-    }
-
-    Ok(())
-}
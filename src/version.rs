@@ -0,0 +1,200 @@
+use clap::ValueEnum;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// How big a version jump is, by the first component that changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateKind {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl fmt::Display for UpdateKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateKind::Patch => write!(f, "patch"),
+            UpdateKind::Minor => write!(f, "minor"),
+            UpdateKind::Major => write!(f, "major"),
+        }
+    }
+}
+
+impl FromStr for UpdateKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "patch" => Ok(UpdateKind::Patch),
+            "minor" => Ok(UpdateKind::Minor),
+            "major" => Ok(UpdateKind::Major),
+            _ => Err(format!("unknown update kind `{}`", s)),
+        }
+    }
+}
+
+/// Parse a version as written by any of the supported package managers
+///
+/// Accepts requirement operators (`^1.2`, `~1.2.3`, `>=1.0`), a leading `v`,
+/// and partial versions, which are padded with zeros (`1.2` is `1.2.0`).
+/// Pre-release tags are lowercased and their numbers split off, so Composer's
+/// `2.0.0-RC10` is `2.0.0-rc.10` and sorts after `2.0.0-rc.9`.
+pub fn parse(version: &str) -> Option<Version> {
+    let version = version
+        .trim()
+        .trim_start_matches(['^', '~', '=', '>', '<', ' '])
+        .trim_start_matches('v');
+
+    // Split off pre-release and build metadata before padding
+    let split = version.find(['-', '+']).unwrap_or(version.len());
+    let (core, rest) = version.split_at(split);
+
+    let mut parts: Vec<&str> = core.split('.').collect();
+    if parts.is_empty() || parts.len() > 3 || parts.iter().any(|p| p.is_empty()) {
+        return None;
+    }
+    parts.resize(3, "0");
+
+    let (pre, build) = rest.split_at(rest.find('+').unwrap_or(rest.len()));
+    let pre = match pre.strip_prefix('-') {
+        Some(pre) => format!("-{}", normalize_pre(pre)),
+        None => String::new(),
+    };
+
+    Version::parse(&format!("{}{}{}", parts.join("."), pre, build)).ok()
+}
+
+// Lowercase a pre-release and split identifiers like `beta2` into `beta.2`,
+// so they compare numerically
+fn normalize_pre(pre: &str) -> String {
+    let mut normalized = String::new();
+    let mut previous: Option<char> = None;
+    for c in pre.chars().map(|c| c.to_ascii_lowercase()) {
+        if let Some(p) = previous
+            && p.is_ascii_alphabetic() != c.is_ascii_alphabetic()
+            && p.is_ascii_alphanumeric()
+            && c.is_ascii_alphanumeric()
+        {
+            normalized.push('.');
+        }
+        normalized.push(c);
+        previous = Some(c);
+    }
+    normalized
+}
+
+/// Whether `version` is newer than `than`
+///
+/// Falls back to comparing the strings when either cannot be parsed.
+pub fn is_newer(version: &str, than: &str) -> bool {
+    match (parse(version), parse(than)) {
+        (Some(version), Some(than)) => version > than,
+        _ => version != than,
+    }
+}
+
+/// Whether a version, or the version a requirement names, is a pre-release
+pub fn is_prerelease(version: &str) -> bool {
+    parse(version).is_some_and(|v| !v.pre.is_empty())
+}
+
+/// The channel of a pre-release, its first identifier: `beta` for
+/// `2.0.0-beta.3`; `None` for stable versions
+pub fn channel(version: &str) -> Option<String> {
+    let version = parse(version)?;
+    let channel = version.pre.as_str().split('.').next()?;
+    (!channel.is_empty()).then(|| channel.to_string())
+}
+
+/// Parse a version range as written in any of the supported manifests
+///
+/// Alternatives may be separated with `||` (npm) or `|` (Composer), and
+/// comparators with commas or spaces, e.g. `>=1.0 <2.0 || ^3`.
+pub fn parse_range(range: &str) -> Option<Vec<VersionReq>> {
+    range
+        .split('|')
+        .map(str::trim)
+        .filter(|alternative| !alternative.is_empty())
+        .map(|alternative| {
+            // Join operators written apart from their version, e.g. `>= 1.0`
+            let mut comparators: Vec<String> = Vec::new();
+            for token in alternative.split([',', ' ']).filter(|t| !t.is_empty()) {
+                match comparators.last_mut() {
+                    Some(last) if last.chars().all(|c| "^~=<>".contains(c)) => last.push_str(token),
+                    _ => comparators.push(token.to_string()),
+                }
+            }
+            VersionReq::parse(&comparators.join(", ")).ok()
+        })
+        .collect::<Option<Vec<_>>>()
+        .filter(|alternatives| !alternatives.is_empty())
+}
+
+/// Whether a version falls within a range; `None` if either cannot be parsed
+pub fn satisfies(version: &str, range: &str) -> Option<bool> {
+    let version = parse(version)?;
+    Some(parse_range(range)?.iter().any(|req| req.matches(&version)))
+}
+
+/// Classify the update from one version to another
+///
+/// Returns `None` if either version cannot be parsed or `to` is not newer.
+pub fn classify(from: &str, to: &str) -> Option<UpdateKind> {
+    let (from, to) = (parse(from)?, parse(to)?);
+    if to <= from {
+        return None;
+    }
+
+    Some(if to.major != from.major {
+        UpdateKind::Major
+    } else if to.minor != from.minor {
+        UpdateKind::Minor
+    } else {
+        UpdateKind::Patch
+    })
+}
+
+/// How far depup may move a dependency, set with `--level`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    /// Patch updates only
+    Patch,
+    /// Minor and patch updates
+    Minor,
+    /// Any update, including breaking majors
+    #[default]
+    Major,
+    /// Updates semver-compatible with the current version, as `^` allows
+    Compatible,
+}
+
+impl Level {
+    /// Whether moving from one version to another is within this level
+    ///
+    /// Versions that cannot be parsed are always allowed.
+    pub fn allows(self, from: &str, to: &str) -> bool {
+        let (Some(from), Some(to)) = (parse(from), parse(to)) else {
+            return true;
+        };
+
+        match self {
+            Level::Patch => (to.major, to.minor) == (from.major, from.minor),
+            Level::Minor => to.major == from.major,
+            Level::Major => true,
+            Level::Compatible if from.major > 0 => to.major == from.major,
+            Level::Compatible if from.minor > 0 => (to.major, to.minor) == (0, from.minor),
+            Level::Compatible => (to.major, to.minor, to.patch) == (0, 0, from.patch),
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("no variant is skipped");
+        f.write_str(value.get_name())
+    }
+}
@@ -0,0 +1,100 @@
+use depup::{cargo, node, php};
+use serde_json::json;
+
+#[test]
+fn test_npm_audit() {
+    let output = json!({
+        "auditReportVersion": 2,
+        "vulnerabilities": {
+            "lodash": {
+                "name": "lodash",
+                "severity": "high",
+                "via": [{
+                    "source": 1094,
+                    "name": "lodash",
+                    "title": "Prototype Pollution in lodash",
+                    "url": "https://github.com/advisories/GHSA-jf85-cpcp-j695",
+                    "severity": "high"
+                }]
+            },
+            "express": { "name": "express", "severity": "high", "via": ["lodash"] }
+        }
+    });
+
+    // Entries only affected through another package carry no advisory of their own
+    let advisories = node::parse_audit(&output).advisories;
+    assert_eq!(advisories.len(), 1);
+    assert_eq!(advisories[0].package, "lodash");
+    assert_eq!(advisories[0].id, "GHSA-jf85-cpcp-j695");
+    assert_eq!(advisories[0].severity.as_deref(), Some("high"));
+}
+
+#[test]
+fn test_composer_audit() {
+    let output = json!({
+        "advisories": {
+            "symfony/http-kernel": [{
+                "advisoryId": "PKSA-2y4d-1fgc-nd3q",
+                "packageName": "symfony/http-kernel",
+                "title": "CVE-2022-24894: Prevent storing cookie headers in HttpCache",
+                "cve": "CVE-2022-24894",
+                "link": "https://symfony.com/cve-2022-24894",
+                "severity": "medium"
+            }]
+        }
+    });
+
+    let advisories = php::parse_audit(&output).advisories;
+    assert_eq!(advisories.len(), 1);
+    assert_eq!(advisories[0].id, "CVE-2022-24894");
+    assert_eq!(advisories[0].package, "symfony/http-kernel");
+
+    // No advisories is reported as an empty list
+    assert!(
+        php::parse_audit(&json!({ "advisories": [] }))
+            .advisories
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_cargo_audit() {
+    let output = json!({
+        "vulnerabilities": {
+            "found": true,
+            "count": 1,
+            "list": [{
+                "advisory": {
+                    "id": "RUSTSEC-2020-0071",
+                    "package": "time",
+                    "title": "Potential segfault in the time crate",
+                    "url": "https://github.com/time-rs/time/issues/293"
+                },
+                "package": { "name": "time", "version": "0.1.45" }
+            }]
+        }
+    });
+
+    let advisories = cargo::parse_audit(&output).advisories;
+    assert_eq!(advisories.len(), 1);
+    assert_eq!(advisories[0].id, "RUSTSEC-2020-0071");
+    assert_eq!(advisories[0].package, "time");
+}
+
+#[test]
+fn test_cargo_audit_yanked() {
+    let output = json!({
+        "vulnerabilities": { "found": false, "count": 0, "list": [] },
+        "warnings": {
+            "yanked": [{
+                "kind": "yanked",
+                "package": { "name": "bytes", "version": "1.0.0" },
+                "advisory": null
+            }]
+        }
+    });
+
+    let audit = cargo::parse_audit(&output);
+    assert!(audit.advisories.is_empty());
+    assert_eq!(audit.yanked, vec!["bytes".to_string()]);
+}
@@ -0,0 +1,132 @@
+use depup::backup::{self, Store};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+#[test]
+fn test_generations_are_kept_apart() {
+    let temp_dir = tempdir().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join("Cargo.toml"), "first").unwrap();
+    fs::write(root.join("Cargo.lock"), "lock").unwrap();
+    let store = Store::new(root);
+
+    // Ecosystems of one run add to the same generation
+    store
+        .back_up("run-1", "cargo", &[Path::new("Cargo.toml")])
+        .unwrap();
+    store
+        .back_up("run-1", "cargo", &[Path::new("Cargo.lock")])
+        .unwrap();
+
+    // A second run does not overwrite the first one's copies
+    fs::write(root.join("Cargo.toml"), "second").unwrap();
+    let copies = store
+        .back_up("run-2", "cargo", &[Path::new("Cargo.toml")])
+        .unwrap();
+    assert_eq!(copies, [root.join(".depup/backups/run-2/files/Cargo.toml")]);
+
+    let generations = store.list().unwrap();
+    let ids: Vec<&str> = generations.iter().map(|g| g.id.as_str()).collect();
+    assert_eq!(ids, ["run-1", "run-2"]);
+    assert_eq!(generations[0].files.len(), 2);
+    assert_eq!(generations[0].files[0].sha256, backup::checksum(b"first"));
+
+    fs::write(root.join("Cargo.toml"), "third").unwrap();
+    let revert = store.restore(&generations[0], &[], false).unwrap();
+    assert_eq!(revert.restored, [Path::new("Cargo.toml")]);
+    assert_eq!(revert.unchanged, [Path::new("Cargo.lock")]);
+    assert_eq!(
+        fs::read_to_string(root.join("Cargo.toml")).unwrap(),
+        "first"
+    );
+}
+
+#[test]
+fn test_corrupted_backup_restores_nothing() {
+    let temp_dir = tempdir().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join("package.json"), "{}").unwrap();
+    let store = Store::new(root);
+    store
+        .back_up("run", "npm", &[Path::new("package.json")])
+        .unwrap();
+
+    fs::write(root.join(".depup/backups/run/files/package.json"), "{ }").unwrap();
+    fs::write(root.join("package.json"), "changed").unwrap();
+
+    let generation = store.generation("run").unwrap();
+    let error = store.restore(&generation, &[], false).unwrap_err();
+    assert_eq!(error.kind(), "backup");
+    assert_eq!(
+        fs::read_to_string(root.join("package.json")).unwrap(),
+        "changed"
+    );
+}
+
+#[test]
+fn test_prune_keeps_newest_generations() {
+    let temp_dir = tempdir().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join("Cargo.toml"), "").unwrap();
+    let store = Store::new(root);
+    for id in ["a", "b", "c"] {
+        store
+            .back_up(id, "cargo", &[Path::new("Cargo.toml")])
+            .unwrap();
+    }
+
+    assert_eq!(store.prune(2).unwrap(), ["a"]);
+    assert_eq!(store.latest().unwrap().unwrap().id, "c");
+    assert_eq!(store.list().unwrap().len(), 2);
+    assert!(store.prune(5).unwrap().is_empty());
+}
+
+#[test]
+fn test_revert_leaves_edited_files_alone() {
+    let temp_dir = tempdir().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join("Cargo.toml"), "before").unwrap();
+    fs::write(root.join("package.json"), "before").unwrap();
+    let store = Store::new(root);
+    store
+        .back_up("run", "cargo", &[Path::new("Cargo.toml")])
+        .unwrap();
+    store
+        .back_up("run", "npm", &[Path::new("package.json")])
+        .unwrap();
+
+    fs::write(root.join("Cargo.toml"), "updated").unwrap();
+    fs::write(root.join("package.json"), "updated").unwrap();
+    store.record_written("run", "cargo").unwrap();
+    store.record_written("run", "npm").unwrap();
+
+    // The user touched Cargo.toml after the update
+    fs::write(root.join("Cargo.toml"), "updated and edited").unwrap();
+
+    let generation = store.generation("run").unwrap();
+    let revert = store.restore(&generation, &[], false).unwrap();
+    assert_eq!(revert.restored, [Path::new("package.json")]);
+    assert_eq!(revert.edited, [Path::new("Cargo.toml")]);
+    assert_eq!(
+        fs::read_to_string(root.join("Cargo.toml")).unwrap(),
+        "updated and edited"
+    );
+
+    // Forcing restores it, and selectors pick files by ecosystem or name
+    let revert = store
+        .restore(&generation, &["cargo".to_string()], true)
+        .unwrap();
+    assert_eq!(revert.restored, [Path::new("Cargo.toml")]);
+    assert!(revert.unchanged.is_empty());
+    let revert = store
+        .restore(&generation, &["package.json".to_string()], false)
+        .unwrap();
+    assert_eq!(revert.unchanged, [Path::new("package.json")]);
+    assert!(
+        store
+            .restore(&generation, &["composer".to_string()], false)
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -0,0 +1,156 @@
+use depup::cache::{Cache, CachedRegistry, Mode};
+use depup::ecosystem::Ecosystem;
+use depup::error::{Error, Result};
+use depup::node::Npm;
+use depup::php::Composer;
+use depup::registry::{Registry, Release};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tempfile::tempdir;
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+// Counts lookups; fails them once `down` is set
+#[derive(Default)]
+struct CountingRegistry {
+    lookups: AtomicUsize,
+    down: bool,
+    location: &'static str,
+}
+
+impl Registry for CountingRegistry {
+    fn releases(&self, _ecosystem: &dyn Ecosystem, package: &str) -> Result<Vec<Release>> {
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        if self.down {
+            return Err(Error::Registry {
+                url: package.to_string(),
+                message: "connection refused".to_string(),
+            });
+        }
+        Ok(vec![Release::new("1.0.0"), Release::new("1.1.0")])
+    }
+
+    fn location(&self, ecosystem: &dyn Ecosystem) -> String {
+        if self.location.is_empty() {
+            ecosystem.default_registry().to_string()
+        } else {
+            self.location.to_string()
+        }
+    }
+}
+
+#[test]
+fn test_lookups_are_cached_until_they_expire() {
+    let temp_dir = tempdir().unwrap();
+    let inner = CountingRegistry::default();
+    let registry = CachedRegistry::new(&inner, Cache::new(temp_dir.path()), HOUR, Mode::Normal);
+
+    let first = registry.releases(&Npm, "@types/node").unwrap();
+    let second = registry.releases(&Npm, "@types/node").unwrap();
+    assert_eq!(first, second);
+    assert_eq!(inner.lookups.load(Ordering::SeqCst), 1);
+
+    // Entries are per ecosystem
+    registry.releases(&Composer, "@types/node").unwrap();
+    assert_eq!(inner.lookups.load(Ordering::SeqCst), 2);
+
+    // --refresh goes to the registry even for fresh entries
+    let refresh = CachedRegistry::new(&inner, Cache::new(temp_dir.path()), HOUR, Mode::Refresh);
+    refresh.releases(&Npm, "@types/node").unwrap();
+    assert_eq!(inner.lookups.load(Ordering::SeqCst), 3);
+
+    // And so does everything once the TTL has passed
+    let expired = CachedRegistry::new(
+        &inner,
+        Cache::new(temp_dir.path()),
+        Duration::ZERO,
+        Mode::Normal,
+    );
+    expired.releases(&Npm, "@types/node").unwrap();
+    assert_eq!(inner.lookups.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+fn test_offline_uses_only_the_cache() {
+    let temp_dir = tempdir().unwrap();
+    let inner = CountingRegistry::default();
+    let offline = CachedRegistry::new(
+        &inner,
+        Cache::new(temp_dir.path()),
+        Duration::ZERO,
+        Mode::Offline,
+    );
+
+    let error = offline.releases(&Npm, "left-pad").unwrap_err();
+    assert_eq!(error.kind(), "offline");
+    assert_eq!(
+        error.to_string(),
+        "No cached npm metadata for left-pad while offline"
+    );
+
+    let online = CachedRegistry::new(&inner, Cache::new(temp_dir.path()), HOUR, Mode::Normal);
+    online.releases(&Npm, "left-pad").unwrap();
+
+    // Expired entries still do when offline
+    assert_eq!(offline.releases(&Npm, "left-pad").unwrap().len(), 2);
+    assert_eq!(inner.lookups.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_stale_entries_and_other_registries() {
+    let temp_dir = tempdir().unwrap();
+    let cache = || Cache::new(temp_dir.path());
+    let inner = CountingRegistry::default();
+    CachedRegistry::new(&inner, cache(), HOUR, Mode::Normal)
+        .releases(&Npm, "left-pad")
+        .unwrap();
+
+    // An expired entry is used when the registry cannot be reached
+    let down = CountingRegistry {
+        down: true,
+        ..CountingRegistry::default()
+    };
+    let releases = CachedRegistry::new(&down, cache(), Duration::ZERO, Mode::Normal)
+        .releases(&Npm, "left-pad")
+        .unwrap();
+    assert_eq!(releases.len(), 2);
+    assert_eq!(down.lookups.load(Ordering::SeqCst), 1);
+
+    // Entries from another registry are never used
+    let mirror = CountingRegistry {
+        down: true,
+        location: "https://npm.example.com",
+        ..CountingRegistry::default()
+    };
+    let error = CachedRegistry::new(&mirror, cache(), HOUR, Mode::Normal)
+        .releases(&Npm, "left-pad")
+        .unwrap_err();
+    assert_eq!(error.kind(), "registry");
+}
+
+#[test]
+fn test_stats_and_clear() {
+    let temp_dir = tempdir().unwrap();
+    let cache = Cache::new(temp_dir.path());
+    assert!(cache.stats().unwrap().is_empty());
+    assert_eq!(cache.clear().unwrap(), 0);
+
+    let inner = CountingRegistry::default();
+    let registry = CachedRegistry::new(&inner, Cache::new(temp_dir.path()), HOUR, Mode::Normal);
+    for package in ["left-pad", "@types/node"] {
+        registry.releases(&Npm, package).unwrap();
+    }
+    registry.releases(&Composer, "monolog/monolog").unwrap();
+
+    let stats = cache.stats().unwrap();
+    assert_eq!(stats.len(), 2);
+    assert_eq!(stats[0].ecosystem, "composer");
+    assert_eq!(stats[0].entries, 1);
+    assert_eq!(stats[1].ecosystem, "npm");
+    assert_eq!(stats[1].entries, 2);
+    assert!(stats[1].bytes > 0);
+    assert!(stats[1].oldest.is_some());
+
+    assert_eq!(cache.clear().unwrap(), 3);
+    assert!(cache.stats().unwrap().is_empty());
+}
@@ -1,12 +1,56 @@
 use assert_cmd::prelude::*;
+use depup::backup::Store;
+use depup::cache::{self, Cache, CachedRegistry};
+use depup::cargo::Cargo;
+use depup::ecosystem::Ecosystem;
+use depup::node::Npm;
+use depup::registry::{Registry, Release};
+use depup::snapshot::Snapshot;
 use predicates::prelude::*;
 use std::fs::{self, File};
 use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::Duration;
 use tempfile::tempdir;
 
 const BIN_NAME: &str = "depup";
 
+// What the registries publish for the packages these tests depend on
+struct Published;
+
+impl Registry for Published {
+    fn releases(&self, _ecosystem: &dyn Ecosystem, package: &str) -> depup::Result<Vec<Release>> {
+        let versions: &[&str] = match package {
+            "colored" => &["2.0.0", "3.0.0"],
+            "clap" => &["4.0.0", "4.4.8"],
+            "left-pad" => &["1.0.0", "1.3.0"],
+            "react" => &["17.0.2"],
+            _ => &[],
+        };
+        Ok(versions.iter().copied().map(Release::new).collect())
+    }
+}
+
+/// A cache directory under `dir` already holding the packages these tests
+/// use, so runs look up versions without the network
+fn seeded_cache(dir: &Path) -> PathBuf {
+    let cache_dir = dir.join("cache");
+    let registry = CachedRegistry::new(
+        &Published,
+        Cache::new(&cache_dir),
+        Duration::ZERO,
+        cache::Mode::Normal,
+    );
+    for package in ["colored", "clap"] {
+        registry.releases(&Cargo, package).unwrap();
+    }
+    for package in ["left-pad", "react"] {
+        registry.releases(&Npm, package).unwrap();
+    }
+    cache_dir
+}
+
 #[test]
 fn test_version_flag() {
     let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
@@ -52,7 +96,7 @@ fn test_custom_path() {
     // we're just checking that the program runs and mentions skipping
     cmd.assert()
         .success()
-        .stdout(predicate::str::contains("Starting dependencies update"));
+        .stderr(predicate::str::contains("Starting dependencies update"));
 }
 
 #[test]
@@ -78,5 +122,619 @@ fn test_no_backup_flag() {
 
     // Check that no backup file was created (this is hard to test completely since npm
     // might not be installed in the test environment, but we can at least check the logic)
-    assert!(!temp_path.join("package.json.backup").exists());
+    assert!(!temp_path.join(".depup").exists());
+}
+
+#[test]
+fn test_dry_run_leaves_files_untouched() {
+    let temp_dir = tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let cache_dir = seeded_cache(temp_path);
+
+    // colored 2.0.0 is older than the published 3.0.0
+    let manifest =
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\ncolored = \"2.0.0\"\n";
+    fs::write(temp_path.join("Cargo.toml"), manifest).unwrap();
+
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.env(cache::DIR_VAR, &cache_dir)
+        .arg(temp_path.to_str().unwrap())
+        .arg("--dry-run")
+        .arg("--diff");
+
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Would update colored from 2.0.0"))
+        .stdout(predicate::str::contains("+++ b/Cargo.toml"))
+        .stderr(predicate::str::contains("No files were changed"));
+
+    assert_eq!(
+        fs::read_to_string(temp_path.join("Cargo.toml")).unwrap(),
+        manifest
+    );
+    assert!(!temp_path.join(".depup").exists());
+    assert!(!temp_path.join("Cargo.lock").exists());
+}
+
+#[test]
+fn test_logging_levels_color_and_file() {
+    let temp_dir = tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let cache_dir = seeded_cache(temp_path);
+    let manifest =
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\ncolored = \"2.0.0\"\n";
+    fs::write(temp_path.join("Cargo.toml"), manifest).unwrap();
+    let log_file = temp_path.join("depup.log");
+
+    // Quiet runs keep stderr free of everything but errors, while the log
+    // file still gets debug detail
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.env(cache::DIR_VAR, &cache_dir)
+        .arg(temp_path.to_str().unwrap())
+        .arg("--dry-run")
+        .arg("-q")
+        .arg("--log-file")
+        .arg(&log_file);
+    cmd.assert().success().stderr(predicate::str::is_empty());
+    let logged = fs::read_to_string(&log_file).unwrap();
+    assert!(logged.contains("INFO    Would update colored from 2.0.0"));
+    assert!(logged.contains("DEBUG"));
+
+    // Forced color escapes the labels; NO_COLOR only affects `auto`
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.env(cache::DIR_VAR, &cache_dir)
+        .arg(temp_path.to_str().unwrap())
+        .arg("--dry-run")
+        .arg("--color")
+        .arg("always")
+        .env("NO_COLOR", "1");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("\x1b[34m[INFO]\x1b[0m"));
+
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.env(cache::DIR_VAR, &cache_dir)
+        .arg(temp_path.to_str().unwrap())
+        .arg("--dry-run")
+        .arg("--color")
+        .arg("never");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("\x1b").not());
+}
+
+#[test]
+fn test_check_exit_codes() {
+    let temp_dir = tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let cache_dir = seeded_cache(temp_path);
+    let manifest = "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\n";
+
+    // Nothing to update
+    fs::write(temp_path.join("Cargo.toml"), manifest).unwrap();
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.env(cache::DIR_VAR, &cache_dir)
+        .arg(temp_path.to_str().unwrap())
+        .arg("--check");
+    cmd.assert()
+        .code(0)
+        .stderr(predicate::str::contains("up to date"));
+
+    // An outdated dependency fails the check without touching the manifest
+    let outdated = format!("{}colored = \"2.0.0\"\n", manifest);
+    fs::write(temp_path.join("Cargo.toml"), &outdated).unwrap();
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.env(cache::DIR_VAR, &cache_dir)
+        .arg(temp_path.to_str().unwrap())
+        .arg("--check");
+    cmd.assert()
+        .code(3)
+        .stderr(predicate::str::contains("1 outdated dependencies"));
+    assert_eq!(
+        fs::read_to_string(temp_path.join("Cargo.toml")).unwrap(),
+        outdated
+    );
+
+    // A manifest that cannot be parsed is an error
+    fs::write(temp_path.join("Cargo.toml"), "[dependencies").unwrap();
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.env(cache::DIR_VAR, &cache_dir)
+        .arg(temp_path.to_str().unwrap())
+        .arg("--check");
+    cmd.assert().code(1);
+}
+
+#[test]
+fn test_check_fails_when_package_manager_is_missing() {
+    let temp_dir = tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    fs::write(
+        temp_path.join("composer.json"),
+        "{\n  \"require\": {\n    \"monolog/monolog\": \"^2.0\"\n  }\n}\n",
+    )
+    .unwrap();
+
+    // With nothing on the PATH composer cannot be found, so nothing is checked
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.arg(temp_path.to_str().unwrap())
+        .arg("--check")
+        .env("PATH", temp_path.join("empty"));
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("composer is not installed"))
+        .stderr(predicate::str::contains("Check failed"));
+}
+
+#[test]
+fn test_json_format() {
+    let temp_dir = tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let cache_dir = seeded_cache(temp_path);
+    let manifest =
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\ncolored = \"2.0.0\"\n";
+    fs::write(temp_path.join("Cargo.toml"), manifest).unwrap();
+
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.env(cache::DIR_VAR, &cache_dir)
+        .arg(temp_path.to_str().unwrap())
+        .args(["--dry-run", "--format", "json"]);
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+
+    // stdout holds exactly one JSON document
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["schema_version"], 1);
+    assert_eq!(json["dry_run"], true);
+    assert_eq!(json["outcome"], "outdated");
+
+    let cargo = &json["ecosystems"][0];
+    assert_eq!(cargo["ecosystem"], "cargo");
+    assert_eq!(cargo["dependencies"][0]["name"], "colored");
+    assert_eq!(cargo["dependencies"][0]["new_requirement"], "^3.0.0");
+    assert!(cargo["dependencies"][0]["skip_reason"].is_null());
+}
+
+#[test]
+fn test_recursive_reports_every_project() {
+    let temp_dir = tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let manifest = "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\n";
+    for project in ["crates/a", "crates/b", "target/package/demo"] {
+        fs::create_dir_all(temp_path.join(project)).unwrap();
+        fs::write(temp_path.join(project).join("Cargo.toml"), manifest).unwrap();
+    }
+
+    // Without --recursive only the directory itself is looked at
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.arg(temp_path.to_str().unwrap())
+        .args(["--dry-run", "--format", "json"]);
+    let output = cmd.output().unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["ecosystems"].as_array().unwrap().len(), 0);
+
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.arg(temp_path.to_str().unwrap())
+        .args(["--recursive", "--dry-run", "--format", "json"]);
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let ecosystems = json["ecosystems"].as_array().unwrap();
+    assert_eq!(ecosystems.len(), 2);
+    assert_eq!(ecosystems[0]["root"], "crates/a");
+    assert_eq!(ecosystems[0]["manifest"], "crates/a/Cargo.toml");
+    assert_eq!(ecosystems[1]["root"], "crates/b");
+
+    // Text output is grouped by project
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.arg(temp_path.to_str().unwrap())
+        .args(["--recursive", "--dry-run"]);
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Project crates/a:"))
+        .stderr(predicate::str::contains("Project crates/b:"));
+}
+
+// Publishes two versions of everything
+struct StaticRegistry;
+
+impl Registry for StaticRegistry {
+    fn releases(&self, _ecosystem: &dyn Ecosystem, _package: &str) -> depup::Result<Vec<Release>> {
+        Ok(vec![Release::new("2.0.0"), Release::new("2.1.0")])
+    }
+}
+
+#[test]
+fn test_offline_runs_from_the_cache() {
+    let temp_dir = tempdir().unwrap();
+    let cache_dir = temp_dir.path().join("cache");
+    let project = temp_dir.path().join("project");
+    fs::create_dir(&project).unwrap();
+    let manifest =
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\ncolored = \"2.0.0\"\n";
+    fs::write(project.join("Cargo.toml"), manifest).unwrap();
+
+    // Versions come from the cache alone: with nothing on the PATH no
+    // package manager can be asked, and colored is not cached yet
+    let run = || {
+        let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+        cmd.env(cache::DIR_VAR, &cache_dir)
+            .env("PATH", temp_dir.path().join("empty"))
+            .arg(&project)
+            .args(["--offline", "--dry-run", "--format", "json"]);
+        let output = cmd.output().unwrap();
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+    let json = run();
+    let cargo = &json["ecosystems"][0];
+    assert!(cargo["dependencies"][0]["new_requirement"].is_null());
+    assert!(cargo["commands"].as_array().unwrap().is_empty());
+    assert!(
+        cargo["warnings"][0]
+            .as_str()
+            .unwrap()
+            .contains("No cached cargo metadata for colored while offline")
+    );
+
+    CachedRegistry::new(
+        &StaticRegistry,
+        Cache::new(&cache_dir),
+        Duration::ZERO,
+        cache::Mode::Normal,
+    )
+    .releases(&Cargo, "colored")
+    .unwrap();
+    let json = run();
+    assert_eq!(
+        json["ecosystems"][0]["dependencies"][0]["new_requirement"],
+        "^2.1.0"
+    );
+
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.env(cache::DIR_VAR, &cache_dir).args(["cache", "stats"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("cargo  1 entries"));
+
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.env(cache::DIR_VAR, &cache_dir).args(["cache", "clear"]);
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Removed 1 cached entries."));
+}
+
+#[test]
+fn test_snapshot_replays_at_its_creation_time() {
+    let temp_dir = tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    fs::write(
+        temp_path.join("package.json"),
+        r#"{"dependencies": {"left-pad": "^1.0.0", "react": "^17.0.2"}}"#,
+    )
+    .unwrap();
+    fs::write(
+        temp_path.join(".depup.toml"),
+        "cooldown = 7\n\n[[rules]]\npackage = \"react\"\nexpires = 2024-06-01\n",
+    )
+    .unwrap();
+    // Long past on the wall clock, but not when the snapshot was taken
+    let snapshot = r#"{
+  "snapshot_version": 1,
+  "created": "2024-01-10T00:00:00Z",
+  "ecosystems": {
+    "npm": {
+      "left-pad": [
+        { "version": "1.0.0", "published": "2023-01-01T00:00:00Z" },
+        { "version": "1.3.0", "published": "2024-01-05T00:00:00Z", "tags": ["latest"] }
+      ],
+      "react": [
+        { "version": "17.0.2", "published": "2021-03-22T00:00:00Z" },
+        { "version": "18.2.0", "published": "2022-06-14T00:00:00Z", "tags": ["latest"] }
+      ]
+    }
+  }
+}"#;
+    fs::write(temp_path.join("snapshot.json"), snapshot).unwrap();
+
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.arg(temp_path)
+        .arg("--registry-snapshot")
+        .arg(temp_path.join("snapshot.json"))
+        .args(["--dry-run", "--format", "json"]);
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let dependencies = &json["ecosystems"][0]["dependencies"];
+    // 1.3.0 was five days old, inside the cooldown
+    assert_eq!(dependencies[0]["name"], "left-pad");
+    assert_eq!(dependencies[0]["skip_reason"]["kind"], "cooldown");
+    // The rule had not expired yet
+    assert_eq!(dependencies[1]["name"], "react");
+    assert_eq!(dependencies[1]["skip_reason"]["kind"], "ignored");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_saving_a_snapshot_keeps_the_plan() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let cache_dir = seeded_cache(temp_path);
+    fs::write(
+        temp_path.join("package.json"),
+        r#"{"dependencies": {"left-pad": "^1.0.0", "react": "^17.0.2"}}"#,
+    )
+    .unwrap();
+
+    // npm has left-pad 1.1.0 installed, newer than the manifest says
+    let bin = temp_path.join("bin");
+    fs::create_dir(&bin).unwrap();
+    fs::write(
+        bin.join("npm"),
+        "#!/bin/sh\necho '{\"left-pad\": {\"current\": \"1.1.0\", \"latest\": \"1.3.0\"}}'\n",
+    )
+    .unwrap();
+    fs::set_permissions(bin.join("npm"), fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap());
+
+    let plan = |extra: &[&Path]| {
+        let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+        cmd.env(cache::DIR_VAR, &cache_dir)
+            .env("PATH", &path)
+            .arg(temp_path)
+            .args(["--dry-run", "--format", "json"])
+            .args(extra);
+        let output = cmd.output().unwrap();
+        assert!(output.status.success());
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json["ecosystems"][0]["dependencies"].clone()
+    };
+    let snapshot = temp_path.join("snapshot.json");
+    let normal = plan(&[]);
+    assert_eq!(normal[0]["current_version"], "1.1.0");
+    assert_eq!(plan(&[Path::new("--save-snapshot"), &snapshot]), normal);
+
+    // The snapshot still holds every dependency for replays
+    let saved = Snapshot::load(&snapshot).unwrap();
+    let packages: Vec<&String> = saved.ecosystems["npm"].keys().collect();
+    assert_eq!(packages, ["left-pad", "react"]);
+    let replayed = plan(&[Path::new("--registry-snapshot"), &snapshot]);
+    assert_eq!(replayed[0]["new_requirement"], normal[0]["new_requirement"]);
+    assert_eq!(replayed[1]["new_requirement"], normal[1]["new_requirement"]);
+}
+
+#[test]
+fn test_registry_snapshot() {
+    let temp_dir = tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    fs::write(
+        temp_path.join("package.json"),
+        r#"{"dependencies": {"left-pad": "^1.0.0"}}"#,
+    )
+    .unwrap();
+    let snapshot = r#"{
+  "snapshot_version": 1,
+  "created": "2025-01-01T00:00:00Z",
+  "ecosystems": {
+    "npm": {
+      "left-pad": [
+        { "version": "1.0.0" },
+        { "version": "1.3.0", "published": "2018-04-09T00:00:00Z", "tags": ["latest"] }
+      ]
+    }
+  }
+}"#;
+    fs::write(temp_path.join("snapshot.json"), snapshot).unwrap();
+
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.arg(temp_path)
+        .arg("--registry-snapshot")
+        .arg(temp_path.join("snapshot.json"))
+        .arg("--save-snapshot")
+        .arg(temp_path.join("used.json"))
+        .args(["--dry-run", "--format", "json"]);
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let npm = &json["ecosystems"][0];
+    assert_eq!(npm["dependencies"][0]["new_requirement"], "^1.3.0");
+    assert!(npm["commands"].as_array().unwrap().is_empty());
+
+    // The saved snapshot holds what the run looked up
+    let used: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(temp_path.join("used.json")).unwrap()).unwrap();
+    let original: serde_json::Value = serde_json::from_str(snapshot).unwrap();
+    assert_eq!(used["ecosystems"], original["ecosystems"]);
+
+    // Packages missing from the snapshot are not looked up anywhere else
+    fs::write(
+        temp_path.join("package.json"),
+        r#"{"dependencies": {"left-pad": "^1.0.0", "lodash": "^4.0.0"}}"#,
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.arg(temp_path)
+        .arg("--registry-snapshot")
+        .arg(temp_path.join("snapshot.json"))
+        .args(["--dry-run", "--format", "json"]);
+    let output = cmd.output().unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json["ecosystems"][0]["warnings"][0],
+        "Could not look up lodash: No npm metadata for lodash in the registry snapshot"
+    );
+}
+
+#[test]
+fn test_config_file() {
+    let temp_dir = tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let cache_dir = seeded_cache(temp_path);
+    let manifest = "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\ncolored = \"2.0.0\"\nclap = \"4.0.0\"\n";
+    fs::write(temp_path.join("Cargo.toml"), manifest).unwrap();
+    fs::write(
+        temp_path.join(".depup.toml"),
+        "format = \"json\"\nlevel = \"minor\"\n\n[ecosystems.cargo]\nignore = [\"clap\"]\n",
+    )
+    .unwrap();
+
+    // The configured format applies without --format
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.env(cache::DIR_VAR, &cache_dir)
+        .arg(temp_path.to_str().unwrap())
+        .arg("--dry-run");
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let deps = &json["ecosystems"][0]["dependencies"];
+    assert_eq!(deps[0]["name"], "colored");
+    assert_eq!(deps[0]["skip_reason"]["kind"], "level_exceeded");
+    assert_eq!(deps[0]["skip_reason"]["update"], "major");
+    assert_eq!(deps[1]["name"], "clap");
+    assert_eq!(deps[1]["skip_reason"]["kind"], "ignored");
+
+    // Flags override the configuration
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.env(cache::DIR_VAR, &cache_dir)
+        .arg(temp_path.to_str().unwrap())
+        .args(["--dry-run", "--format", "text"]);
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Skipping clap: ignored"));
+
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.args(["config", "show", temp_path.to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("level = \"minor\""))
+        .stdout(predicate::str::contains("[ecosystems.cargo]"))
+        .stdout(predicate::str::contains("ignore = [\"clap\"]"));
+}
+
+#[test]
+fn test_inline_annotations() {
+    let temp_dir = tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let cache_dir = seeded_cache(temp_path);
+    let manifest = "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\ncolored = \"2.0.0\" # depup: pin <3\n";
+    fs::write(temp_path.join("Cargo.toml"), manifest).unwrap();
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.env(cache::DIR_VAR, &cache_dir)
+        .arg(temp_path.to_str().unwrap())
+        .arg("--check");
+    cmd.assert().code(0).stderr(predicate::str::contains(
+        "Skipping colored: pinned to <3 (annotated in Cargo.toml)",
+    ));
+}
+
+#[test]
+fn test_backups_and_revert() {
+    let temp_dir = tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    fs::write(temp_path.join("Cargo.toml"), "original").unwrap();
+
+    let store = Store::new(temp_path);
+    store
+        .back_up("20250101T000000Z-0001", "cargo", &[Path::new("Cargo.toml")])
+        .unwrap();
+    fs::write(temp_path.join("Cargo.toml"), "updated").unwrap();
+
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.args(["backups", "list", temp_path.to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("20250101T000000Z-0001"))
+        .stdout(predicate::str::contains("Cargo.toml"));
+
+    // Unknown runs are refused
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.args(["revert", "--run", "nope", temp_path.to_str().unwrap()]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("no such backup"));
+
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.args([
+        "revert",
+        "--run",
+        "20250101T000000Z-0001",
+        temp_path.to_str().unwrap(),
+    ]);
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Restored Cargo.toml"))
+        .stderr(predicate::str::contains("1 file(s) restored"));
+    assert_eq!(
+        fs::read_to_string(temp_path.join("Cargo.toml")).unwrap(),
+        "original"
+    );
+
+    // Selecting nothing is an error rather than a silent no-op
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.args(["revert", "--only", "npm", temp_path.to_str().unwrap()]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No files in backup"));
+}
+
+#[test]
+fn test_logging_options_go_with_subcommands() {
+    let temp_dir = tempdir().unwrap();
+    let temp_path = temp_dir.path().to_str().unwrap();
+
+    // Logging options may come before or after the subcommand
+    for args in [
+        ["-q", "revert", temp_path, "--force"],
+        ["--color", "never", "revert", temp_path],
+        ["revert", "--color", "never", temp_path],
+    ] {
+        let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+        cmd.args(args);
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("No backups found"));
+    }
+
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.args(["-v", "backups", "list", temp_path]);
+    cmd.assert().success();
+
+    // Update options still cannot be combined with one
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.args(["--dry-run", "revert", temp_path]);
+    cmd.assert().code(2).stderr(predicate::str::contains(
+        "the subcommand 'revert' cannot be used with '--dry-run'",
+    ));
+}
+
+#[test]
+fn test_failed_lockfile_refresh_rolls_back() {
+    let temp_dir = tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let cache_dir = seeded_cache(temp_path);
+
+    // The path dependency does not exist, so `cargo update` fails after
+    // colored has been bumped in the manifest
+    let manifest = "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\ncolored = \"2.0.0\"\nmissing = { path = \"missing\" }\n";
+    fs::write(temp_path.join("Cargo.toml"), manifest).unwrap();
+
+    let mut cmd = Command::cargo_bin(BIN_NAME).unwrap();
+    cmd.env(cache::DIR_VAR, &cache_dir)
+        .arg(temp_path.to_str().unwrap());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Restored Cargo.toml and Cargo.lock after cargo update failed",
+        ))
+        .stderr(predicate::str::contains("Dependency update completed").not())
+        .stderr(predicate::str::contains("depup revert").not());
+
+    assert_eq!(
+        fs::read_to_string(temp_path.join("Cargo.toml")).unwrap(),
+        manifest
+    );
+    assert!(!temp_path.join("Cargo.lock").exists());
 }
@@ -0,0 +1,104 @@
+use depup::config::{self, Config, EcosystemConfig};
+use depup::render::Format;
+use depup::version::Level;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_merge_precedence() {
+    let user: Config = toml::from_str(
+        r#"
+        ignore = ["left-pad"]
+        format = "json"
+
+        [ecosystems.npm]
+        registry = "https://npm.example.com"
+        post-update = ["npm run build"]
+        verify = ["npm test"]
+        prerelease = "next"
+        "#,
+    )
+    .unwrap();
+    let project: Config = toml::from_str(
+        r#"
+        ignore = ["react"]
+        format = "markdown"
+
+        [ecosystems.npm]
+        enabled = false
+        ignore = ["jest"]
+        "#,
+    )
+    .unwrap();
+
+    let merged = user.merge(project);
+
+    // Scalars come from the later file, ignore lists accumulate
+    assert_eq!(merged.format, Some(Format::Markdown));
+    assert_eq!(merged.ignore, ["left-pad", "react"]);
+    assert_eq!(
+        merged.ecosystem("npm"),
+        EcosystemConfig {
+            enabled: Some(false),
+            ignore: vec!["jest".to_string()],
+            registry: Some("https://npm.example.com".to_string()),
+            cooldown: None,
+            prerelease: Some("next".to_string()),
+            timeout: None,
+            post_update: vec!["npm run build".to_string()],
+            verify: vec!["npm test".to_string()],
+        }
+    );
+
+    assert_eq!(merged.prerelease_for("npm"), Some("next"));
+    assert_eq!(merged.prerelease_for("cargo"), None);
+
+    assert!(!merged.is_enabled("npm"));
+    assert!(merged.is_enabled("cargo"));
+    assert!(merged.is_ignored("npm", "jest"));
+    assert!(merged.is_ignored("cargo", "react"));
+    assert!(!merged.is_ignored("cargo", "jest"));
+}
+
+#[test]
+fn test_project_config_is_found_upward() {
+    let temp_dir = tempdir().unwrap();
+    let nested = temp_dir.path().join("packages").join("web");
+    fs::create_dir_all(&nested).unwrap();
+    fs::write(
+        temp_dir.path().join(config::FILE_NAME),
+        "level = \"minor\"\n",
+    )
+    .unwrap();
+
+    let found = config::find_project_config(&nested).unwrap();
+    assert_eq!(
+        found,
+        temp_dir
+            .path()
+            .canonicalize()
+            .unwrap()
+            .join(config::FILE_NAME)
+    );
+    assert_eq!(config::read(&found).unwrap().level, Some(Level::Minor));
+}
+
+#[test]
+fn test_invalid_config_reports_line() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join(config::FILE_NAME);
+    fs::write(&path, "level = \"minor\"\nlevle = \"major\"\n").unwrap();
+
+    let message = config::read(&path).unwrap_err().to_string();
+    assert!(message.starts_with("Failed to parse"));
+    assert!(message.contains("line 2: unknown field `levle`"));
+}
+
+#[test]
+fn test_defaults_are_filled_for_show() {
+    let shown = Config::default().with_defaults(&["npm"]).to_toml();
+    assert!(shown.contains("level = \"major\""));
+    assert!(shown.contains("format = \"text\""));
+    assert!(shown.contains("backup = true"));
+    assert!(shown.contains("[ecosystems.npm]\nenabled = true"));
+}
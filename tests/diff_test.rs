@@ -0,0 +1,29 @@
+use depup::ManifestChange;
+use depup::diff;
+use std::path::{Path, PathBuf};
+
+#[test]
+fn test_unified_diff() {
+    let before = "[dependencies]\nserde = \"1.0\"\ncolored = \"2.0.0\"\n";
+    let after = "[dependencies]\nserde = \"1.0\"\ncolored = \"^3.0.0\"\n";
+
+    let diff = diff::unified(Path::new("Cargo.toml"), before, after);
+    assert!(diff.starts_with("--- a/Cargo.toml\n+++ b/Cargo.toml\n"));
+    assert!(diff.contains("@@ -1,3 +1,3 @@"));
+    assert!(diff.contains("\n-colored = \"2.0.0\"\n+colored = \"^3.0.0\"\n"));
+    assert!(diff.contains("\n serde = \"1.0\"\n"));
+}
+
+#[test]
+fn test_manifest_change_diff() {
+    let change = ManifestChange {
+        path: PathBuf::from("package.json"),
+        before: "{\n  \"a\": \"1\"\n}\n".to_string(),
+        after: "{\n  \"a\": \"2\"\n}\n".to_string(),
+    };
+
+    let diff = change.unified_diff();
+    assert!(diff.contains("--- a/package.json"));
+    assert!(diff.contains("-  \"a\": \"1\""));
+    assert!(diff.contains("+  \"a\": \"2\""));
+}
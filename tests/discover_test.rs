@@ -0,0 +1,80 @@
+use depup::cargo::Cargo;
+use depup::discover;
+use depup::ecosystem::Ecosystem;
+use depup::node::Npm;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+fn touch(root: &Path, file: &str) {
+    let path = root.join(file);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, "{}").unwrap();
+}
+
+#[test]
+fn test_projects_skip_ignored_and_installed_directories() {
+    let temp_dir = tempdir().unwrap();
+    let root = temp_dir.path();
+    touch(root, "package.json");
+    touch(root, "crates/core/Cargo.toml");
+    touch(root, "web/package.json");
+    touch(root, "web/composer.json");
+    // Installed packages and build output carry manifests of their own
+    touch(root, "node_modules/left-pad/package.json");
+    touch(root, "web/vendor/monolog/monolog/composer.json");
+    touch(root, "target/package/demo-0.1.0/Cargo.toml");
+    // As do hidden and ignored directories
+    touch(root, ".depup/backups/run/files/package.json");
+    touch(root, "fixtures/broken/package.json");
+    fs::write(root.join(".gitignore"), "fixtures/\n").unwrap();
+
+    let projects: Vec<PathBuf> = discover::projects(root)
+        .into_iter()
+        .map(|project| project.strip_prefix(root).unwrap().to_path_buf())
+        .collect();
+    assert_eq!(
+        projects,
+        vec![
+            PathBuf::new(),
+            PathBuf::from("crates/core"),
+            PathBuf::from("web"),
+        ]
+    );
+}
+
+#[test]
+fn test_projects_of_an_empty_tree() {
+    let temp_dir = tempdir().unwrap();
+    touch(temp_dir.path(), "docs/README.json");
+    assert!(discover::projects(temp_dir.path()).is_empty());
+}
+
+#[test]
+fn test_workspace_members_find_their_root() {
+    let temp_dir = tempdir().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"crates/*\"]\n",
+    )
+    .unwrap();
+    touch(&root, "crates/core/Cargo.toml");
+    fs::write(root.join("package.json"), r#"{"workspaces": ["web"]}"#).unwrap();
+    touch(&root, "web/package.json");
+    touch(&root, "tools/lint/package.json");
+    fs::write(root.join("tools/package.json"), "{}").unwrap();
+
+    assert_eq!(
+        Cargo.workspace_root(&root.join("crates/core")),
+        Some(root.clone())
+    );
+    assert_eq!(Npm.workspace_root(&root.join("web")), Some(root.clone()));
+    // The nearest workspace counts, not just the nearest manifest
+    assert_eq!(
+        Npm.workspace_root(&root.join("tools/lint")),
+        Some(root.clone())
+    );
+    // A workspace root keeps its own lockfile
+    assert_eq!(Cargo.workspace_root(&root), None);
+}
@@ -0,0 +1,226 @@
+use depup::Error;
+use depup::cargo::Cargo;
+use depup::ecosystem::{self, Candidate, Ecosystem};
+use depup::node::Npm;
+use depup::php::Composer;
+use depup::rules::Rule;
+use depup::version::UpdateKind;
+use std::path::Path;
+
+// Annotation rules record where they came from
+fn annotated(rule: Rule) -> Rule {
+    Rule {
+        reason: Some("annotated in Cargo.toml".to_string()),
+        ..rule
+    }
+}
+
+const CARGO_TOML: &str = r#"[package]
+name = "example"
+
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+colored = "^2.0.0" # terminal colors
+local = { path = "../local" }
+
+[dev-dependencies.tempfile]
+version = "3.0"
+"#;
+
+#[test]
+fn test_registry_order() {
+    let names: Vec<&str> = ecosystem::registry().iter().map(|e| e.name()).collect();
+    assert_eq!(names, ["composer", "npm", "cargo"]);
+}
+
+#[test]
+fn test_cargo_collect_dependencies() {
+    let deps = Cargo.collect_dependencies(CARGO_TOML).unwrap();
+
+    // Path dependencies have no version requirement and are skipped
+    let found: Vec<(&str, &str, &str)> = deps
+        .iter()
+        .map(|d| (d.name.as_str(), d.requirement.as_str(), d.section.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("serde", "1.0", "dependencies"),
+            ("colored", "^2.0.0", "dependencies"),
+            ("tempfile", "3.0", "dev-dependencies"),
+        ]
+    );
+}
+
+#[test]
+fn test_dependency_lines() {
+    // Line numbers point at the declaration, or at its `version` key for
+    // dependencies written as tables
+    let deps = Cargo.collect_dependencies(CARGO_TOML).unwrap();
+    let lines: Vec<Option<usize>> = deps.iter().map(|d| d.line).collect();
+    assert_eq!(lines, [Some(5), Some(6), Some(10)]);
+
+    let package_json = "{\n  \"dependencies\": {\n    \"left-pad\": \"^1.0.0\"\n  }\n}\n";
+    let deps = Npm.collect_dependencies(package_json).unwrap();
+    assert_eq!(deps[0].line, Some(3));
+}
+
+#[test]
+fn test_cargo_annotations() {
+    let manifest = r#"[dependencies]
+colored = "2.0.0" # depup: ignore
+serde = { version = "1.0" } # depup: pin <2
+clap = "4.0" # keep sorted
+
+[dependencies.tempfile] # depup: ignore major
+version = "3.0"
+"#;
+    let deps = Cargo.collect_dependencies(manifest).unwrap();
+
+    assert_eq!(deps[0].rules, [Rule::ignore("colored")].map(annotated));
+    assert_eq!(deps[1].rules, [Rule::pin("serde", "<2")].map(annotated));
+    assert!(deps[2].rules.is_empty());
+    assert_eq!(deps[3].rules[0].ignore_updates, [UpdateKind::Major]);
+
+    // Typos are reported rather than silently ignored
+    let error = Cargo
+        .collect_dependencies("[dependencies]\nserde = \"1.0\" # depup: pinn <2\n")
+        .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .ends_with("line 2: unknown depup annotation `pinn <2`")
+    );
+}
+
+#[test]
+fn test_json_annotations() {
+    let package_json = r#"{
+  "dependencies": { "react": "^18.0.0", "@types/node": "^20.0.0", "left-pad": "^1.0.0" },
+  "depup": { "ignore": ["@types/*"], "pin": { "react": "<19" } }
+}"#;
+    let deps = Npm.collect_dependencies(package_json).unwrap();
+    assert_eq!(deps[0].rules[0].allow.as_deref(), Some("<19"));
+    assert_eq!(
+        deps[1].rules[0].reason.as_deref(),
+        Some("annotated in package.json")
+    );
+    assert!(deps[2].rules.is_empty());
+
+    // Composer keeps custom settings under `extra`
+    let composer_json = r#"{ "require": { "symfony/console": "^6.0" }, "extra": { "depup": { "ignore": ["symfony/*"] } } }"#;
+    let deps = Composer.collect_dependencies(composer_json).unwrap();
+    assert_eq!(deps[0].rules.len(), 1);
+
+    let invalid = r#"{ "depup": { "pin": { "react": "soon" } } }"#;
+    assert!(Npm.collect_dependencies(invalid).is_err());
+}
+
+#[test]
+fn test_cargo_plan_and_apply() {
+    let deps = Cargo.collect_dependencies(CARGO_TOML).unwrap();
+    let candidates = vec![
+        Candidate {
+            name: "serde".to_string(),
+            current: "1.0".to_string(),
+            latest: "1.0.190".to_string(),
+        },
+        Candidate {
+            name: "colored".to_string(),
+            current: "2.0.0".to_string(),
+            latest: "3.0.0".to_string(),
+        },
+        Candidate {
+            name: "tempfile".to_string(),
+            current: "3.0".to_string(),
+            latest: "3.8.1".to_string(),
+        },
+    ];
+
+    let edits = Cargo.plan_edits(&deps, &candidates);
+    assert_eq!(edits.len(), 3);
+    assert_eq!(edits[0].requirement, "^1.0.190");
+
+    let updated = Cargo.apply(CARGO_TOML, &edits).unwrap();
+    assert!(updated.contains(r#"serde = { version = "^1.0.190", features = ["derive"] }"#));
+    assert!(updated.contains(r#"colored = "^3.0.0" # terminal colors"#));
+    assert!(updated.contains(r#"version = "^3.8.1""#));
+    assert!(updated.contains(r#"local = { path = "../local" }"#));
+}
+
+#[test]
+fn test_cargo_apply_keeps_table_annotations() {
+    let manifest = "[dependencies.clap]\nversion = \"4.0.0\" # depup: ignore major\n";
+    let deps = Cargo.collect_dependencies(manifest).unwrap();
+    let candidates = vec![Candidate {
+        name: "clap".to_string(),
+        current: "4.0.0".to_string(),
+        latest: "4.4.8".to_string(),
+    }];
+
+    let edits = Cargo.plan_edits(&deps, &candidates);
+    let updated = Cargo.apply(manifest, &edits).unwrap();
+    assert_eq!(
+        updated,
+        "[dependencies.clap]\nversion = \"^4.4.8\" # depup: ignore major\n"
+    );
+
+    // The policy still applies on the next run
+    let deps = Cargo.collect_dependencies(&updated).unwrap();
+    assert_eq!(deps[0].rules[0].ignore_updates, [UpdateKind::Major]);
+}
+
+#[test]
+fn test_json_manifests() {
+    let package_json = r#"{
+  "name": "example",
+  "dependencies": { "left-pad": "^1.0.0" },
+  "devDependencies": { "jest": "^29.0.0" }
+}"#;
+    let deps = Npm.collect_dependencies(package_json).unwrap();
+    assert_eq!(deps.len(), 2);
+    assert_eq!(deps[1].section, "devDependencies");
+
+    let candidates = vec![Candidate {
+        name: "jest".to_string(),
+        current: "29.0.0".to_string(),
+        latest: "29.7.0".to_string(),
+    }];
+    let edits = Npm.plan_edits(&deps, &candidates);
+    let updated = Npm.apply(package_json, &edits).unwrap();
+    assert!(updated.contains(r#""jest": "^29.7.0""#));
+    assert!(updated.contains(r#""left-pad": "^1.0.0""#));
+
+    let composer_json =
+        r#"{ "require": { "php": ">=8.1" }, "require-dev": { "phpunit/phpunit": "^10.0" } }"#;
+    let deps = Composer.collect_dependencies(composer_json).unwrap();
+    assert_eq!(deps.len(), 2);
+    assert_eq!(deps[1].name, "phpunit/phpunit");
+    assert!(Composer.collect_dependencies("not json").is_err());
+}
+
+#[test]
+fn test_parse_errors_are_typed() {
+    let err = Cargo.collect_dependencies("[dependencies").unwrap_err();
+    match &err {
+        Error::Parse { path, .. } => assert_eq!(path, Path::new("Cargo.toml")),
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+    assert!(err.to_string().starts_with("Failed to parse Cargo.toml"));
+}
+
+#[test]
+fn test_json_formatting_is_preserved() {
+    // Four-space indentation, unsorted keys and a trailing newline
+    let composer_json = "{\n    \"name\": \"acme/app\",\n    \"require\": {\n        \"symfony/console\": \"^6.0\",\n        \"monolog/monolog\": \"^2.0\"\n    }\n}\n";
+    let deps = Composer.collect_dependencies(composer_json).unwrap();
+    let candidates = vec![Candidate {
+        name: "monolog/monolog".to_string(),
+        current: "2.0.0".to_string(),
+        latest: "3.5.0".to_string(),
+    }];
+
+    let edits = Composer.plan_edits(&deps, &candidates);
+    let updated = Composer.apply(composer_json, &edits).unwrap();
+    assert_eq!(updated, composer_json.replace("\"^2.0\"", "\"^3.5.0\""));
+}
@@ -0,0 +1,46 @@
+use depup::jobs;
+use depup::log;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_map_keeps_order_and_limit() {
+    let running = AtomicUsize::new(0);
+    let most = AtomicUsize::new(0);
+    let items: Vec<u64> = (0..12).collect();
+
+    let results = jobs::map(&items, 3, |&item| {
+        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+        most.fetch_max(now, Ordering::SeqCst);
+        // Later items finish first
+        thread::sleep(Duration::from_millis(30 - item * 2));
+        running.fetch_sub(1, Ordering::SeqCst);
+        item * 10
+    });
+
+    assert_eq!(results, items.iter().map(|i| i * 10).collect::<Vec<_>>());
+    assert!(most.load(Ordering::SeqCst) > 1);
+    assert!(most.load(Ordering::SeqCst) <= 3);
+}
+
+#[test]
+fn test_nested_jobs_output_stays_grouped() {
+    let items = ["a", "b", "c"];
+    let (_, lines) = log::hold(|| {
+        log::info("start");
+        jobs::map(&items, 3, |item| {
+            log::info(&format!("{} one", item));
+            thread::sleep(Duration::from_millis(10));
+            log::info(&format!("{} two", item));
+        });
+    });
+
+    let lines: Vec<&str> = lines.iter().map(|line| line.message.as_str()).collect();
+    assert_eq!(
+        lines,
+        [
+            "start", "a one", "a two", "b one", "b two", "c one", "c two"
+        ]
+    );
+}
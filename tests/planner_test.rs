@@ -0,0 +1,207 @@
+use chrono::{TimeDelta, Utc};
+use depup::ecosystem::{self, Ecosystem};
+use depup::error::Result;
+use depup::registry::{Registry, Release};
+use depup::runner::SystemRunner;
+use depup::version::Level;
+use depup::{Options, SkipReason, cargo::Cargo};
+use std::fs;
+use tempfile::{TempDir, tempdir};
+
+// Serves a fixed list of releases for every package without a list of its own
+struct FakeRegistry(Vec<Release>, Vec<(&'static str, Vec<Release>)>);
+
+impl FakeRegistry {
+    fn versions(versions: &[&str]) -> FakeRegistry {
+        FakeRegistry(releases(versions), Vec::new())
+    }
+
+    fn with(mut self, package: &'static str, releases: Vec<Release>) -> FakeRegistry {
+        self.1.push((package, releases));
+        self
+    }
+}
+
+impl Registry for FakeRegistry {
+    fn releases(&self, _ecosystem: &dyn Ecosystem, package: &str) -> Result<Vec<Release>> {
+        let own = self.1.iter().find(|(name, _)| *name == package);
+        Ok(own.map_or(&self.0, |(_, releases)| releases).clone())
+    }
+}
+
+fn releases(versions: &[&str]) -> Vec<Release> {
+    versions.iter().map(|v| Release::new(v)).collect()
+}
+
+fn published(version: &str, days_ago: i64) -> Release {
+    Release {
+        published: Some(Utc::now() - TimeDelta::days(days_ago)),
+        ..Release::new(version)
+    }
+}
+
+// A project depending on colored 2.0.0 and clap 4.0.0, both behind their
+// newest releases
+fn project() -> TempDir {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join("Cargo.toml"),
+        "[dependencies]\ncolored = \"2.0.0\"\nclap = \"4.0.0\"\n",
+    )
+    .unwrap();
+    temp_dir
+}
+
+fn dry_run() -> Options {
+    Options {
+        dry_run: true,
+        ..Options::default()
+    }
+}
+
+#[test]
+fn test_level_picks_newest_version_within_bound() {
+    let project = project();
+    let root = project.path();
+    let mut options = dry_run();
+    options.config.level = Some(Level::Minor);
+    let registry = FakeRegistry::versions(&["2.0.4", "2.1.0", "2.2.0-rc.1", "3.0.0"])
+        .with("clap", releases(&["4.0.0", "4.4.8"]));
+
+    let report = ecosystem::update(&Cargo, root, &options, &registry, &SystemRunner);
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+
+    // colored's newest release is a major, so the newest 2.x is used instead
+    let colored = &report.dependencies[0];
+    assert_eq!(colored.latest_version.as_deref(), Some("3.0.0"));
+    assert_eq!(colored.target_version.as_deref(), Some("2.1.0"));
+    assert_eq!(colored.new_requirement.as_deref(), Some("^2.1.0"));
+    assert!(
+        report
+            .change
+            .as_ref()
+            .unwrap()
+            .after
+            .contains("colored = \"^2.1.0\"")
+    );
+
+    // clap's newest release is within the level, so it is used as is
+    let clap = &report.dependencies[1];
+    assert_eq!(clap.target_version.as_deref(), Some("4.4.8"));
+
+    // Without a suitable release the dependency is skipped with the reason
+    let report = ecosystem::update(
+        &Cargo,
+        root,
+        &options,
+        &FakeRegistry::versions(&["3.0.0"]),
+        &SystemRunner,
+    );
+    assert!(matches!(
+        report.dependencies[0].skip_reason,
+        Some(SkipReason::LevelExceeded {
+            level: Level::Minor,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_cooldown_passes_over_new_versions() {
+    let project = project();
+    let root = project.path();
+    let mut options = dry_run();
+
+    // A cooldown passes over versions that are too new, including when the
+    // newest one is otherwise allowed
+    options.config.cooldown = Some(7);
+    let registry = FakeRegistry(
+        vec![
+            published("2.1.0", 30),
+            published("2.2.0", 8),
+            published("3.0.0", 2),
+        ],
+        Vec::new(),
+    )
+    .with("clap", vec![published("4.4.8", 1)]);
+    let report = ecosystem::update(&Cargo, root, &options, &registry, &SystemRunner);
+    assert_eq!(
+        report.dependencies[0].target_version.as_deref(),
+        Some("2.2.0")
+    );
+    assert_eq!(
+        report.dependencies[0].latest_version.as_deref(),
+        Some("3.0.0")
+    );
+
+    // clap 4.4.8 is a day old and nothing between it and 4.0.0 qualifies
+    let clap = &report.dependencies[1];
+    assert!(!clap.is_update());
+    assert_eq!(
+        clap.skip_reason.as_ref().unwrap().to_string(),
+        format!(
+            "4.4.8 was published on {}, within the 7-day cooldown",
+            (Utc::now() - TimeDelta::days(1)).format("%Y-%m-%d")
+        )
+    );
+}
+
+#[test]
+fn test_prerelease_needs_channel() {
+    let project = project();
+    let root = project.path();
+    let mut options = dry_run();
+
+    // Pre-releases are passed over unless their channel is opted into, which
+    // may go past the newest stable version
+    let registry =
+        FakeRegistry::versions(&["3.0.0", "3.1.0-beta.2", "3.1.0-beta.10", "3.1.0-rc.1"])
+            .with("clap", releases(&["4.0.0", "4.4.8"]));
+    let report = ecosystem::update(&Cargo, root, &options, &registry, &SystemRunner);
+    assert_eq!(
+        report.dependencies[0].target_version.as_deref(),
+        Some("3.0.0")
+    );
+
+    options.config.prerelease = Some("beta".to_string());
+    let report = ecosystem::update(&Cargo, root, &options, &registry, &SystemRunner);
+    assert_eq!(
+        report.dependencies[0].target_version.as_deref(),
+        Some("3.1.0-beta.10")
+    );
+    // clap has no pre-releases, so its newest version stands
+    assert_eq!(
+        report.dependencies[1].target_version.as_deref(),
+        Some("4.4.8")
+    );
+}
+
+#[test]
+fn test_declarations_follow_their_own_rules() {
+    let temp_dir = tempdir().unwrap();
+    let manifest = "[dependencies]\ncolored = \"2.0.0\"\n\n[dev-dependencies]\ncolored = \"2.0.0\" # depup: ignore\n";
+    fs::write(temp_dir.path().join("Cargo.toml"), manifest).unwrap();
+    let registry = FakeRegistry::versions(&["2.0.0", "3.0.0"]);
+
+    let report = ecosystem::update(
+        &Cargo,
+        temp_dir.path(),
+        &dry_run(),
+        &registry,
+        &SystemRunner,
+    );
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+
+    // Only the unannotated declaration is rewritten
+    let after = &report.change.as_ref().unwrap().after;
+    assert!(after.contains("[dependencies]\ncolored = \"^3.0.0\"\n"));
+    assert!(after.contains("[dev-dependencies]\ncolored = \"2.0.0\" # depup: ignore\n"));
+    assert_eq!(
+        report.dependencies[0].new_requirement.as_deref(),
+        Some("^3.0.0")
+    );
+    assert!(matches!(
+        report.dependencies[1].skip_reason,
+        Some(SkipReason::Ignored { .. })
+    ));
+}
@@ -0,0 +1,129 @@
+use depup::cargo::{self, Cargo};
+use depup::ecosystem::Ecosystem;
+use depup::node::{self, Npm};
+use depup::php::{self, Composer};
+use depup::registry::{self, Release};
+use depup::version;
+use serde_json::json;
+
+#[test]
+fn test_releases_urls() {
+    let url = |ecosystem: &dyn Ecosystem, package| {
+        ecosystem
+            .releases_url(ecosystem.default_registry(), package)
+            .unwrap()
+    };
+
+    assert_eq!(url(&Cargo, "a"), "https://index.crates.io/1/a");
+    assert_eq!(url(&Cargo, "syn"), "https://index.crates.io/3/s/syn");
+    assert_eq!(
+        url(&Cargo, "Serde_JSON"),
+        "https://index.crates.io/se/rd/serde_json"
+    );
+    assert_eq!(
+        url(&Npm, "@types/node"),
+        "https://registry.npmjs.org/@types%2fnode"
+    );
+    assert_eq!(
+        url(&Composer, "symfony/console"),
+        "https://repo.packagist.org/p2/symfony/console.json"
+    );
+
+    // Names the crates.io index cannot hold have no file in it
+    for name in ["", "café", "../a"] {
+        assert_eq!(Cargo.releases_url(Cargo.default_registry(), name), None);
+    }
+}
+
+#[test]
+fn test_parse_cargo_index() {
+    let body = concat!(
+        r#"{"name":"colored","vers":"2.0.0","yanked":false}"#,
+        "\n",
+        r#"{"name":"colored","vers":"2.0.1","yanked":true,"pubtime":"2023-06-01T10:00:00Z"}"#,
+        "\n"
+    );
+
+    let releases = cargo::parse_index(body);
+    assert_eq!(releases.len(), 2);
+    assert_eq!(releases[0], Release::new("2.0.0"));
+    assert!(releases[1].yanked);
+    assert_eq!(
+        releases[1].published.unwrap().to_rfc3339(),
+        "2023-06-01T10:00:00+00:00"
+    );
+}
+
+#[test]
+fn test_parse_npm_packument() {
+    let packument = json!({
+        "name": "react",
+        "dist-tags": { "latest": "18.2.0", "next": "19.0.0-rc.1" },
+        "versions": { "18.2.0": {}, "19.0.0-rc.1": {} },
+        "time": { "created": "2011-10-26T17:46:21.942Z", "18.2.0": "2022-06-14T19:46:38.369Z" }
+    });
+
+    let releases = node::parse_packument(&packument);
+    assert_eq!(releases.len(), 2);
+    assert!(releases[0].published.is_some());
+    assert!(releases[1].published.is_none());
+    assert_eq!(releases[0].tags, ["latest"]);
+    assert_eq!(releases[1].tags, ["next"]);
+}
+
+#[test]
+fn test_parse_minified_packagist() {
+    let metadata = json!({
+        "minified": "composer/2.0",
+        "packages": {
+            "monolog/monolog": [
+                { "name": "monolog/monolog", "version": "3.5.0", "time": "2023-10-27T15:32:31+00:00" },
+                { "version": "3.4.0" },
+                { "version": "dev-main", "time": "__unset" }
+            ]
+        }
+    });
+
+    let releases = php::parse_packagist(&metadata, "monolog/monolog");
+    let versions: Vec<&str> = releases.iter().map(|r| r.version.as_str()).collect();
+    assert_eq!(versions, ["3.5.0", "3.4.0", "dev-main"]);
+
+    // Omitted fields carry over from the previous entry
+    assert_eq!(releases[1].published, releases[0].published);
+    assert!(releases[2].published.is_none());
+}
+
+#[test]
+fn test_newest_allowed_release() {
+    let releases = vec![
+        Release::new("1.9.0"),
+        Release::new("2.0.0"),
+        Release::new("1.10.0"),
+        Release::new("1.11.0-beta.1"),
+        Release {
+            yanked: true,
+            ..Release::new("1.12.0")
+        },
+    ];
+    let below_two = |release: &Release| release.version.starts_with("1.");
+
+    // Sorted by version, not by string; yanked versions are skipped
+    assert_eq!(
+        registry::newest(&releases, "1.2.0", below_two).as_deref(),
+        Some("1.11.0-beta.1")
+    );
+    let stable =
+        |release: &Release| below_two(release) && !version::is_prerelease(&release.version);
+    assert_eq!(
+        registry::newest(&releases, "1.2.0", stable).as_deref(),
+        Some("1.10.0")
+    );
+    assert_eq!(
+        registry::newest(&releases, "1.10.0", stable).as_deref(),
+        None
+    );
+    assert_eq!(
+        registry::newest(&releases, "1.11.0-alpha", below_two).as_deref(),
+        Some("1.11.0-beta.1")
+    );
+}
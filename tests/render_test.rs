@@ -0,0 +1,193 @@
+use depup::render::{self, Format};
+use depup::version::UpdateKind;
+use depup::{DependencyReport, EcosystemReport, Error, Outcome, SkipReason, UpdateReport};
+use std::path::{Path, PathBuf};
+
+fn sample_report() -> UpdateReport {
+    let mut cargo = EcosystemReport::new(&depup::cargo::Cargo);
+    cargo.dependencies = vec![
+        DependencyReport {
+            name: "colored".to_string(),
+            section: "dependencies".to_string(),
+            old_requirement: "2.0.0".to_string(),
+            new_requirement: Some("^3.0.0".to_string()),
+            current_version: Some("2.0.0".to_string()),
+            latest_version: Some("3.0.0".to_string()),
+            target_version: Some("3.0.0".to_string()),
+            update_kind: Some(UpdateKind::Major),
+            skip_reason: None,
+            advisories: Vec::new(),
+            yanked: false,
+            line: Some(7),
+        },
+        DependencyReport {
+            name: "serde".to_string(),
+            section: "dependencies".to_string(),
+            old_requirement: "1.0".to_string(),
+            new_requirement: None,
+            current_version: None,
+            latest_version: None,
+            target_version: None,
+            update_kind: None,
+            skip_reason: Some(SkipReason::UpToDate),
+            advisories: Vec::new(),
+            yanked: false,
+            line: Some(8),
+        },
+    ];
+
+    let mut npm = EcosystemReport::new(&depup::node::Npm);
+    npm.errors.push(Error::Parse {
+        path: PathBuf::from("package.json"),
+        message: "expected value at line 1 column 1".to_string(),
+    });
+
+    UpdateReport {
+        dry_run: true,
+        backup: None,
+        ecosystems: vec![cargo, npm],
+    }
+}
+
+#[test]
+fn test_text_is_not_rendered() {
+    assert!(render::render(&sample_report(), Format::Text).is_none());
+}
+
+#[test]
+fn test_yanked_versions_fail_the_check() {
+    let mut report = sample_report();
+    report.ecosystems.truncate(1);
+    assert_eq!(report.outcome(), Outcome::Outdated);
+
+    // A yanked version is as much a reason to act as an advisory
+    report.ecosystems[0].dependencies[1].yanked = true;
+    assert_eq!(report.outcome(), Outcome::Vulnerable);
+    assert_eq!(report.outcome().exit_code(), 4);
+}
+
+#[test]
+fn test_json_document() {
+    let document = render::render(&sample_report(), Format::Json).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&document).unwrap();
+
+    assert_eq!(json["schema_version"], render::json::SCHEMA_VERSION);
+    assert_eq!(json["tool"]["name"], "depup");
+    assert_eq!(json["outcome"], "failed");
+
+    let cargo = &json["ecosystems"][0];
+    assert_eq!(cargo["manifest"], "Cargo.toml");
+    assert_eq!(
+        cargo["dependencies"][1]["skip_reason"]["kind"],
+        "up_to_date"
+    );
+
+    let error = &json["ecosystems"][1]["errors"][0];
+    assert_eq!(error["kind"], "parse");
+    assert!(
+        error["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Failed to parse package.json")
+    );
+}
+
+#[test]
+fn test_markdown_tables() {
+    let document = render::render(&sample_report(), Format::Markdown).unwrap();
+
+    assert!(document.starts_with("## Dependency updates\n"));
+    assert!(document.contains("### cargo\n"));
+    assert!(document.contains("| colored | `2.0.0` → `3.0.0` | major | dependencies |"));
+    // Up-to-date dependencies are not listed
+    assert!(!document.contains("| serde |"));
+    assert!(document.contains("### npm\n\nNo updates.\n"));
+    assert!(document.contains("- **Error:** Failed to parse package.json"));
+}
+
+#[test]
+fn test_html_page() {
+    let mut report = sample_report();
+    report.ecosystems[0].dependencies[0].name = "<script>".to_string();
+
+    let document = render::render(&report, Format::Html).unwrap();
+    assert!(document.starts_with("<!DOCTYPE html>"));
+    assert!(document.contains("<h2>cargo</h2>"));
+    assert!(document.contains("<td>&lt;script&gt;</td><td>2.0.0</td><td>3.0.0</td>"));
+    assert!(document.contains("<td class=\"major\">major</td>"));
+    assert!(!document.contains("<script>"));
+}
+
+#[test]
+fn test_sarif_results_point_at_manifest_lines() {
+    let mut report = sample_report();
+    report.ecosystems[0].dependencies[1].yanked = true;
+
+    let document = render::render(&report, Format::Sarif).unwrap();
+    let sarif: serde_json::Value = serde_json::from_str(&document).unwrap();
+    assert_eq!(sarif["version"], "2.1.0");
+
+    let run = &sarif["runs"][0];
+    assert_eq!(run["tool"]["driver"]["name"], "depup");
+
+    let results = run["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+
+    // Results follow the manifest order
+    let outdated = &results[0];
+    assert_eq!(outdated["ruleId"], "outdated-dependency");
+    assert_eq!(outdated["level"], "warning");
+    let location = &outdated["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "Cargo.toml");
+    assert_eq!(location["region"]["startLine"], 7);
+
+    // The up-to-date but yanked dependency is still reported
+    let yanked = &results[1];
+    assert_eq!(yanked["ruleId"], "yanked-dependency");
+    assert_eq!(yanked["level"], "error");
+    assert_eq!(
+        yanked["locations"][0]["physicalLocation"]["region"]["startLine"],
+        8
+    );
+}
+
+#[test]
+fn test_junit_suites() {
+    let document = render::render(&sample_report(), Format::Junit).unwrap();
+
+    assert!(document.starts_with("<?xml"));
+    assert!(
+        document.contains("<testsuites name=\"depup\" tests=\"3\" failures=\"1\" errors=\"1\">")
+    );
+    assert!(document.contains(
+        "<testsuite name=\"cargo\" tests=\"2\" failures=\"1\" errors=\"0\" skipped=\"0\">"
+    ));
+    // Up-to-date dependencies pass
+    assert!(document.contains("<testcase name=\"serde\" classname=\"cargo.dependencies\"/>"));
+    assert!(document.contains(
+        "<failure type=\"outdated-dependency\" message=\"colored 2.0.0 can be updated to 3.0.0 (major)\"/>"
+    ));
+    assert!(document.contains("<error type=\"parse\" message=\"Failed to parse package.json"));
+}
+
+#[test]
+fn test_projects_are_told_apart() {
+    let mut report = sample_report();
+    report.ecosystems.truncate(1);
+    let mut other = sample_report().ecosystems.remove(0);
+    report.ecosystems[0].relocate(Path::new("crates/a"));
+    other.relocate(Path::new("crates/b"));
+    report.ecosystems.push(other);
+
+    let markdown = render::render(&report, Format::Markdown).unwrap();
+    assert!(markdown.contains("### cargo (crates/a)\n"));
+    assert!(markdown.contains("### cargo (crates/b)\n"));
+
+    let html = render::render(&report, Format::Html).unwrap();
+    assert!(html.contains("<h2>cargo (crates/b)</h2>"));
+
+    let junit = render::render(&report, Format::Junit).unwrap();
+    assert!(junit.contains("<testsuite name=\"cargo (crates/a)\""));
+    assert!(junit.contains("<testsuite name=\"cargo (crates/b)\""));
+    assert!(junit.contains("<testcase name=\"serde\" classname=\"crates/b.cargo.dependencies\"/>"));
+}
@@ -0,0 +1,149 @@
+use chrono::NaiveDate;
+use depup::SkipReason;
+use depup::config::{self, Config};
+use depup::rules::{self, Rule};
+use depup::version::UpdateKind;
+use std::fs;
+use tempfile::tempdir;
+
+fn rules(toml: &str) -> Vec<Rule> {
+    toml::from_str::<Config>(toml).unwrap().rules
+}
+
+fn today() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()
+}
+
+#[test]
+fn test_pin_to_range() {
+    let rules = rules(
+        r#"
+        [[rules]]
+        package = "react"
+        allow = "<19"
+        reason = "waiting on the router"
+        "#,
+    );
+
+    // Updates inside the range go ahead
+    let within = rules::evaluate(&rules, "npm", "react", Some(("18.2.0", "18.3.1")), today());
+    assert_eq!(within, None);
+
+    let beyond = rules::evaluate(&rules, "npm", "react", Some(("18.2.0", "19.0.0")), today());
+    assert_eq!(
+        beyond,
+        Some(SkipReason::Pinned {
+            allow: "<19".to_string(),
+            reason: Some("waiting on the router".to_string()),
+        })
+    );
+    assert_eq!(
+        beyond.unwrap().to_string(),
+        "pinned to <19 (waiting on the router)"
+    );
+}
+
+#[test]
+fn test_glob_and_update_kinds() {
+    let rules = rules(
+        r#"
+        [[rules]]
+        package = "@types/*"
+        ignore-updates = ["major"]
+
+        [[rules]]
+        package = "symfony/*"
+        ecosystem = "composer"
+        "#,
+    );
+
+    assert_eq!(
+        rules::evaluate(
+            &rules,
+            "npm",
+            "@types/node",
+            Some(("20.0.0", "22.1.0")),
+            today()
+        ),
+        Some(SkipReason::UpdateKindIgnored {
+            update: UpdateKind::Major,
+            reason: None,
+        })
+    );
+    assert_eq!(
+        rules::evaluate(
+            &rules,
+            "npm",
+            "@types/node",
+            Some(("20.0.0", "20.1.0")),
+            today()
+        ),
+        None
+    );
+
+    // Whole-package ignores apply even without a known update
+    assert_eq!(
+        rules::evaluate(&rules, "composer", "symfony/console", None, today()),
+        Some(SkipReason::Ignored { reason: None })
+    );
+    assert_eq!(
+        rules::evaluate(&rules, "npm", "symfony/console", None, today()),
+        None
+    );
+}
+
+#[test]
+fn test_expired_rules_are_inactive() {
+    let rules = rules(
+        r#"
+        [[rules]]
+        package = "lodash"
+        expires = 2025-05-31
+        "#,
+    );
+
+    assert!(rules[0].is_expired(today()));
+    assert_eq!(
+        rules::evaluate(&rules, "npm", "lodash", None, today()),
+        None
+    );
+
+    // The rule still applies on its last day
+    let last_day = NaiveDate::from_ymd_opt(2025, 5, 31).unwrap();
+    assert!(rules::evaluate(&rules, "npm", "lodash", None, last_day).is_some());
+}
+
+#[test]
+fn test_ignore_lists_accept_globs() {
+    let config: Config = toml::from_str(
+        r#"
+        ignore = ["@babel/*"]
+
+        [ecosystems.cargo]
+        ignore = ["serde*"]
+        "#,
+    )
+    .unwrap();
+
+    assert!(config.is_ignored("npm", "@babel/core"));
+    assert!(config.is_ignored("cargo", "serde_json"));
+    assert!(!config.is_ignored("npm", "serde_json"));
+}
+
+#[test]
+fn test_invalid_rules_are_rejected() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join(config::FILE_NAME);
+
+    fs::write(
+        &path,
+        "[[rules]]\npackage = \"react\"\nallow = \"not a range\"\n",
+    )
+    .unwrap();
+    let message = config::read(&path).unwrap_err().to_string();
+    assert!(message.contains("invalid version range `not a range` for `react`"));
+
+    fs::write(&path, "ignore = [\"[abc\"]\n").unwrap();
+    let message = config::read(&path).unwrap_err().to_string();
+    assert!(message.contains("invalid package pattern `[abc`"));
+}
@@ -0,0 +1,500 @@
+use depup::Options;
+use depup::backup::Store;
+use depup::cache;
+use depup::cargo::Cargo;
+use depup::config::EcosystemConfig;
+use depup::ecosystem::{self, Ecosystem};
+use depup::error::{Error, Result};
+use depup::node::Npm;
+use depup::registry::{Registry, Release};
+use depup::runner::{self, CommandOutput, CommandRunner, Recorder, SystemRunner};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+
+// Answers commands from a script of (command line prefix, exit code, stdout)
+struct ScriptedRunner(Vec<(&'static str, i32, &'static str)>);
+
+impl CommandRunner for ScriptedRunner {
+    fn run(&self, command: &mut Command, _timeout: Option<Duration>) -> Result<CommandOutput> {
+        let argv = runner::argv(command);
+        let line = argv.join(" ");
+        let (_, code, stdout) = self
+            .0
+            .iter()
+            .find(|(prefix, ..)| line.starts_with(prefix))
+            .unwrap_or_else(|| panic!("unexpected command: {}", line));
+        Ok(CommandOutput {
+            argv,
+            dir: command.get_current_dir().map(Path::to_path_buf),
+            code: Some(*code),
+            stdout: stdout.to_string(),
+            stderr: if *code == 0 { "" } else { "npm ERR! code E404" }.to_string(),
+            duration: Duration::ZERO,
+        })
+    }
+}
+
+// Lets `npm outdated` through and notes whether two lockfile updates ever ran
+// at the same time
+#[derive(Default)]
+struct OverlapRunner {
+    updating: AtomicUsize,
+    overlapped: AtomicBool,
+}
+
+impl CommandRunner for OverlapRunner {
+    fn run(&self, command: &mut Command, _timeout: Option<Duration>) -> Result<CommandOutput> {
+        let argv = runner::argv(command);
+        let stdout = if argv[1] == "outdated" {
+            r#"{"left-pad": {"current": "1.0.0", "latest": "1.3.0"}}"#
+        } else {
+            if self.updating.fetch_add(1, Ordering::SeqCst) > 0 {
+                self.overlapped.store(true, Ordering::SeqCst);
+            }
+            thread::sleep(Duration::from_millis(100));
+            self.updating.fetch_sub(1, Ordering::SeqCst);
+            ""
+        };
+        Ok(CommandOutput {
+            argv,
+            dir: command.get_current_dir().map(Path::to_path_buf),
+            code: Some(0),
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+            duration: Duration::ZERO,
+        })
+    }
+}
+
+// Lets `npm outdated` through and times every other command out
+struct TimeoutRunner;
+
+impl CommandRunner for TimeoutRunner {
+    fn run(&self, command: &mut Command, _timeout: Option<Duration>) -> Result<CommandOutput> {
+        let argv = runner::argv(command);
+        let mut output = CommandOutput {
+            argv,
+            dir: command.get_current_dir().map(Path::to_path_buf),
+            code: Some(1),
+            stdout: r#"{"left-pad": {"current": "1.0.0", "latest": "1.3.0"}}"#.to_string(),
+            stderr: String::new(),
+            duration: Duration::ZERO,
+        };
+        if output.argv[1] == "outdated" {
+            return Ok(output);
+        }
+        output.code = None;
+        output.stdout = "fetching left-pad".to_string();
+        output.duration = Duration::from_secs(5);
+        Err(Error::Timeout {
+            command: output.command_line(),
+            seconds: 5,
+            output: Box::new(output),
+        })
+    }
+}
+
+// Rewrites the workspace lockfile on `npm update`, as npm does when run in a
+// member, and fails every shell command
+struct WorkspaceRunner(PathBuf);
+
+impl CommandRunner for WorkspaceRunner {
+    fn run(&self, command: &mut Command, _timeout: Option<Duration>) -> Result<CommandOutput> {
+        let argv = runner::argv(command);
+        if argv[1] == "update" {
+            fs::write(&self.0, "updated").unwrap();
+        }
+        Ok(CommandOutput {
+            code: Some(if argv[0] == "sh" { 1 } else { 0 }),
+            stdout: if argv[1] == "outdated" {
+                r#"{"left-pad": {"current": "1.0.0", "latest": "1.3.0"}}"#
+            } else {
+                ""
+            }
+            .to_string(),
+            argv,
+            dir: command.get_current_dir().map(Path::to_path_buf),
+            stderr: String::new(),
+            duration: Duration::ZERO,
+        })
+    }
+}
+
+struct NoRegistry;
+
+impl Registry for NoRegistry {
+    fn releases(&self, _ecosystem: &dyn Ecosystem, _package: &str) -> Result<Vec<Release>> {
+        Ok(Vec::new())
+    }
+}
+
+// Knows of one newer left-pad, and of the same versions of any other package
+struct LeftPadRegistry;
+
+impl Registry for LeftPadRegistry {
+    fn releases(&self, _ecosystem: &dyn Ecosystem, _package: &str) -> Result<Vec<Release>> {
+        Ok(vec![Release::new("1.0.0"), Release::new("1.3.0")])
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_system_runner_captures_output() {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", "echo out; echo err >&2; exit 3"]);
+    let output = SystemRunner.run(&mut cmd, None).unwrap();
+
+    assert_eq!(output.argv, ["sh", "-c", "echo out; echo err >&2; exit 3"]);
+    assert_eq!(output.code, Some(3));
+    assert!(!output.success());
+    assert_eq!(output.stdout, "out\n");
+    assert_eq!(output.stderr, "err\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_system_runner_enforces_timeout() {
+    // The shell's own child holds the pipes open as well
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", "echo started; sleep 10"]);
+    let started = Instant::now();
+    let error = SystemRunner
+        .run(&mut cmd, Some(Duration::from_millis(500)))
+        .unwrap_err();
+
+    let Error::Timeout { output, .. } = error else {
+        panic!("expected a timeout, got {:?}", error);
+    };
+    assert!(started.elapsed() < Duration::from_secs(5));
+    // What it printed before being killed is kept
+    assert_eq!(output.stdout, "started\n");
+    assert_eq!(output.code, None);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_timeout_kills_what_the_command_started() {
+    let temp_dir = tempdir().unwrap();
+    let marker = temp_dir.path().join("marker");
+
+    // The shell's child would create the marker after the timeout
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(format!(
+        "(sleep 1; touch '{}') & sleep 10",
+        marker.display()
+    ));
+    let error = SystemRunner
+        .run(&mut cmd, Some(Duration::from_millis(300)))
+        .unwrap_err();
+    assert_eq!(error.kind(), "timeout");
+
+    thread::sleep(Duration::from_millis(1500));
+    assert!(!marker.exists());
+}
+
+#[test]
+fn test_missing_binary_is_an_error() {
+    let mut cmd = Command::new("this_command_does_not_exist_12345");
+    let recorder = Recorder::new(&SystemRunner);
+    let error = recorder.run(&mut cmd, None).unwrap_err();
+    assert_eq!(error.kind(), "command");
+
+    // The attempt is still listed
+    let outputs = recorder.into_outputs();
+    assert_eq!(outputs.len(), 1);
+    assert_eq!(outputs[0].argv, ["this_command_does_not_exist_12345"]);
+    assert_eq!(outputs[0].code, None);
+}
+
+#[test]
+fn test_failed_update_is_reported_and_recorded() {
+    let temp_dir = tempdir().unwrap();
+    let manifest = "{\n  \"dependencies\": {\n    \"left-pad\": \"^1.0.0\"\n  }\n}\n";
+    fs::write(temp_dir.path().join("package.json"), manifest).unwrap();
+
+    let runner = ScriptedRunner(vec![
+        (
+            "npm outdated",
+            1,
+            r#"{"left-pad": {"current": "1.0.0", "latest": "1.3.0"}}"#,
+        ),
+        ("npm update", 1, ""),
+    ]);
+    let options = Options {
+        backup: false,
+        ..Options::default()
+    };
+    let report = ecosystem::update(&Npm, temp_dir.path(), &options, &NoRegistry, &runner);
+
+    // The exit code and last stderr line end up in a typed error
+    assert_eq!(report.errors.len(), 1);
+    assert!(matches!(
+        report.errors[0],
+        Error::Exit { code: Some(1), .. }
+    ));
+    assert_eq!(
+        report.errors[0].to_string(),
+        "Failed to run npm update: exited with code 1: npm ERR! code E404"
+    );
+    assert!(report.rolled_back);
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("package.json")).unwrap(),
+        manifest
+    );
+
+    let commands: Vec<String> = report.commands.iter().map(|c| c.command_line()).collect();
+    assert_eq!(commands, ["npm outdated --json", "npm update"]);
+    // Commands run in the project, not wherever depup was started
+    assert!(
+        report
+            .commands
+            .iter()
+            .all(|c| c.dir.as_deref() == Some(temp_dir.path()))
+    );
+}
+
+#[test]
+fn test_timed_out_command_is_recorded() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join("package.json"),
+        "{\n  \"dependencies\": {\n    \"left-pad\": \"^1.0.0\"\n  }\n}\n",
+    )
+    .unwrap();
+
+    let options = Options {
+        backup: false,
+        ..Options::default()
+    };
+    let report = ecosystem::update(&Npm, temp_dir.path(), &options, &NoRegistry, &TimeoutRunner);
+
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].kind(), "timeout");
+    assert!(report.rolled_back);
+    let update = &report.commands[1];
+    assert_eq!(update.command_line(), "npm update");
+    assert_eq!(update.code, None);
+    assert_eq!(update.stdout, "fetching left-pad");
+    assert_eq!(update.duration, Duration::from_secs(5));
+}
+
+#[test]
+fn test_failed_verification_rolls_back_the_batch() {
+    let temp_dir = tempdir().unwrap();
+    let manifest = "{\n  \"dependencies\": {\n    \"left-pad\": \"^1.0.0\"\n  }\n}\n";
+    fs::write(temp_dir.path().join("package.json"), manifest).unwrap();
+
+    let runner = ScriptedRunner(vec![
+        (
+            "npm outdated",
+            1,
+            r#"{"left-pad": {"current": "1.0.0", "latest": "1.3.0"}}"#,
+        ),
+        ("npm update", 0, ""),
+        ("sh -c npm test", 1, ""),
+    ]);
+    let mut options = Options {
+        backup: false,
+        ..Options::default()
+    };
+    options.config.ecosystems.insert(
+        "npm".to_string(),
+        EcosystemConfig {
+            verify: vec!["npm test".to_string()],
+            ..EcosystemConfig::default()
+        },
+    );
+    let report = ecosystem::update(&Npm, temp_dir.path(), &options, &NoRegistry, &runner);
+
+    // The error names the batch that broke the project
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].kind(), "verification");
+    assert_eq!(
+        report.errors[0].to_string(),
+        "Verification failed after updating npm packages left-pad: \
+         Failed to run sh -c npm test: exited with code 1: npm ERR! code E404"
+    );
+    assert!(report.rolled_back);
+    assert!(!report.manifest_updated);
+    assert!(report.warnings.contains(
+        &"Restored package.json and package-lock.json after verification failed".to_string()
+    ));
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("package.json")).unwrap(),
+        manifest
+    );
+
+    let commands: Vec<String> = report.commands.iter().map(|c| c.command_line()).collect();
+    assert_eq!(
+        commands,
+        ["npm outdated --json", "npm update", "sh -c npm test"]
+    );
+}
+
+#[test]
+fn test_failed_post_update_command_rolls_back() {
+    let temp_dir = tempdir().unwrap();
+    let manifest = "{\n  \"dependencies\": {\n    \"left-pad\": \"^1.0.0\"\n  }\n}\n";
+    fs::write(temp_dir.path().join("package.json"), manifest).unwrap();
+
+    let runner = ScriptedRunner(vec![
+        (
+            "npm outdated",
+            1,
+            r#"{"left-pad": {"current": "1.0.0", "latest": "1.3.0"}}"#,
+        ),
+        ("npm update", 0, ""),
+        ("sh -c npm run build", 1, ""),
+    ]);
+    let mut options = Options {
+        backup: false,
+        ..Options::default()
+    };
+    options.config.ecosystems.insert(
+        "npm".to_string(),
+        EcosystemConfig {
+            post_update: vec!["npm run build".to_string()],
+            verify: vec!["npm test".to_string()],
+            ..EcosystemConfig::default()
+        },
+    );
+    let report = ecosystem::update(&Npm, temp_dir.path(), &options, &NoRegistry, &runner);
+
+    // Verification never starts on a half-finished update
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].kind(), "exit");
+    assert!(report.rolled_back);
+    assert!(
+        report.warnings.contains(
+            &"Restored package.json and package-lock.json after a post-update command failed"
+                .to_string()
+        )
+    );
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("package.json")).unwrap(),
+        manifest
+    );
+    let commands: Vec<String> = report.commands.iter().map(|c| c.command_line()).collect();
+    assert_eq!(
+        commands,
+        ["npm outdated --json", "npm update", "sh -c npm run build"]
+    );
+}
+
+#[test]
+fn test_workspace_lockfile_is_backed_up_and_rolled_back() {
+    let temp_dir = tempdir().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join("package.json"), r#"{"workspaces": ["web"]}"#).unwrap();
+    fs::write(root.join("package-lock.json"), "original").unwrap();
+    let member = root.join("web");
+    fs::create_dir(&member).unwrap();
+    fs::write(
+        member.join("package.json"),
+        "{\n  \"dependencies\": {\n    \"left-pad\": \"^1.0.0\"\n  }\n}\n",
+    )
+    .unwrap();
+
+    let runner = WorkspaceRunner(root.join("package-lock.json"));
+    let mut options = Options {
+        backup_root: Some(root.to_path_buf()),
+        ..Options::default()
+    };
+    options.config.ecosystems.insert(
+        "npm".to_string(),
+        EcosystemConfig {
+            verify: vec!["npm test".to_string()],
+            ..EcosystemConfig::default()
+        },
+    );
+    let report = ecosystem::update(&Npm, &member, &options, &NoRegistry, &runner);
+
+    // npm changed the lockfile at the workspace root, which is put back too
+    assert!(report.rolled_back);
+    assert_eq!(
+        fs::read_to_string(root.join("package-lock.json")).unwrap(),
+        "original"
+    );
+    let generation = Store::new(root).generation(&options.run_id).unwrap();
+    let files: Vec<&Path> = generation.files.iter().map(|f| f.path.as_path()).collect();
+    assert_eq!(
+        files,
+        [
+            Path::new("web/package.json"),
+            Path::new("package-lock.json")
+        ]
+    );
+}
+
+#[test]
+fn test_offline_update_stays_off_the_network() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join("package.json"),
+        "{\n  \"dependencies\": {\n    \"left-pad\": \"^1.0.0\"\n  }\n}\n",
+    )
+    .unwrap();
+
+    // `npm outdated` would ask the registry, so only the update may run
+    let runner = ScriptedRunner(vec![("npm update --offline", 0, "")]);
+    let options = Options {
+        backup: false,
+        cache: cache::Mode::Offline,
+        resolve_from_registry: true,
+        audit: true,
+        ..Options::default()
+    };
+    let report = ecosystem::update(&Npm, temp_dir.path(), &options, &LeftPadRegistry, &runner);
+
+    assert!(report.errors.is_empty());
+    assert!(report.lockfile_refreshed);
+    assert_eq!(
+        report.warnings,
+        ["Could not audit npm dependencies while offline"]
+    );
+    let commands: Vec<String> = report.commands.iter().map(|c| c.command_line()).collect();
+    assert_eq!(commands, ["npm update --offline"]);
+}
+
+#[test]
+fn test_updates_in_one_directory_take_turns() {
+    let temp_dir = tempdir().unwrap();
+    let root = temp_dir.path();
+    fs::write(
+        root.join("package.json"),
+        "{\n  \"dependencies\": {\n    \"left-pad\": \"^1.0.0\"\n  }\n}\n",
+    )
+    .unwrap();
+    fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\ncolored = \"1.0.0\"\n",
+    )
+    .unwrap();
+
+    let runner = OverlapRunner::default();
+    let options = Options {
+        backup: false,
+        ..Options::default()
+    };
+    let reports: Vec<_> = thread::scope(|scope| {
+        let ecosystems: [&dyn Ecosystem; 2] = [&Npm, &Cargo];
+        let handles: Vec<_> = ecosystems
+            .into_iter()
+            .map(|ecosystem| {
+                let (options, runner) = (&options, &runner);
+                scope.spawn(move || {
+                    ecosystem::update(ecosystem, root, options, &LeftPadRegistry, runner)
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    assert!(reports.iter().all(|r| r.lockfile_refreshed));
+    assert!(!runner.overlapped.load(Ordering::SeqCst));
+}
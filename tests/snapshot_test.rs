@@ -0,0 +1,103 @@
+use depup::registry::{Registry, Release};
+use depup::snapshot::{Recording, Snapshot};
+use depup::{Options, node::Npm, run_with};
+use std::fs;
+use tempfile::tempdir;
+
+fn releases(versions: &[&str]) -> Vec<Release> {
+    versions.iter().map(|v| Release::new(v)).collect()
+}
+
+fn snapshot() -> Snapshot {
+    let mut snapshot = Snapshot::default();
+    // The `latest` dist-tag wins over a newer pre-release
+    let mut left_pad = releases(&["1.0.0", "1.3.0", "2.0.0-beta.1"]);
+    left_pad[1].tags.push("latest".to_string());
+    snapshot.insert("npm", "left-pad", left_pad);
+    snapshot.insert("npm", "react", releases(&["17.0.2", "18.3.1"]));
+    snapshot.insert(
+        "composer",
+        "monolog/monolog",
+        releases(&["2.9.0", "3.7.0", "3.8.0-RC1"]),
+    );
+    snapshot.insert("cargo", "serde", releases(&["1.0.0", "1.0.210"]));
+    snapshot
+}
+
+#[test]
+fn test_snapshot_round_trip() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("snapshot.json");
+    let snapshot = snapshot();
+    snapshot.save(&path).unwrap();
+    assert_eq!(Snapshot::load(&path).unwrap(), snapshot);
+
+    assert_eq!(snapshot.releases(&Npm, "react").unwrap().len(), 2);
+    let error = snapshot.releases(&Npm, "lodash").unwrap_err();
+    assert_eq!(error.kind(), "snapshot");
+    assert_eq!(
+        error.to_string(),
+        "No npm metadata for lodash in the registry snapshot"
+    );
+
+    fs::write(
+        &path,
+        r#"{"snapshot_version": 9, "created": "2025-01-01T00:00:00Z", "ecosystems": {}}"#,
+    )
+    .unwrap();
+    let error = Snapshot::load(&path).unwrap_err();
+    assert!(error.to_string().contains("unsupported snapshot version 9"));
+}
+
+#[test]
+fn test_update_plan_from_a_snapshot() {
+    let temp_dir = tempdir().unwrap();
+    let root = temp_dir.path();
+    fs::write(
+        root.join("package.json"),
+        r#"{"dependencies": {"left-pad": "^1.0.0", "react": "^17.0.2", "local": "file:../local"}}"#,
+    )
+    .unwrap();
+    fs::write(
+        root.join("composer.json"),
+        r#"{"require": {"php": ">=8.1", "monolog/monolog": "^2.9"}}"#,
+    )
+    .unwrap();
+    fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"1.0\"\n",
+    )
+    .unwrap();
+
+    let snapshot = snapshot();
+    let recording = Recording::new(&snapshot);
+    let options = Options {
+        dry_run: true,
+        resolve_from_registry: true,
+        ..Options::default()
+    };
+    let report = run_with(root, &options, &recording);
+
+    let planned: Vec<(String, String)> = report
+        .ecosystems
+        .iter()
+        .flat_map(|e| e.updates())
+        .map(|d| (d.name.clone(), d.to_version().to_string()))
+        .collect();
+    assert_eq!(
+        planned,
+        [
+            ("monolog/monolog", "3.7.0"),
+            ("left-pad", "1.3.0"),
+            ("react", "18.3.1"),
+            ("serde", "1.0.210"),
+        ]
+        .map(|(name, version)| (name.to_string(), version.to_string()))
+    );
+    assert!(report.ecosystems.iter().all(|e| e.warnings.is_empty()));
+    assert!(report.ecosystems.iter().all(|e| e.commands.is_empty()));
+
+    // Exactly what was used is recorded: everything, as every package was
+    // looked up once
+    assert_eq!(recording.into_snapshot().ecosystems, snapshot.ecosystems);
+}
@@ -0,0 +1,45 @@
+use depup::transaction::Transaction;
+use depup::utils;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+#[test]
+fn test_write_atomic_replaces_in_place() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("package.json");
+    fs::write(&path, "old").unwrap();
+
+    utils::write_atomic(&path, b"new").unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+
+    // The temporary file is renamed away, not left next to the original
+    let entries: Vec<_> = fs::read_dir(temp_dir.path()).unwrap().collect();
+    assert_eq!(entries.len(), 1);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        utils::write_atomic(&path, b"newer").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
+
+#[test]
+fn test_rollback_restores_every_file() {
+    let temp_dir = tempdir().unwrap();
+    let manifest = temp_dir.path().join("Cargo.toml");
+    let lockfile = temp_dir.path().join("Cargo.lock");
+    fs::write(&manifest, "before").unwrap();
+
+    let transaction = Transaction::begin(&[manifest.as_path(), lockfile.as_path()]).unwrap();
+    transaction.write(&manifest, "after").unwrap();
+    // The package manager created a lockfile that was not there before
+    fs::write(&lockfile, "generated").unwrap();
+
+    transaction.rollback().unwrap();
+    assert_eq!(fs::read_to_string(&manifest).unwrap(), "before");
+    assert!(!Path::new(&lockfile).exists());
+}
@@ -29,9 +29,9 @@ fn test_ensure_backups_in_gitignore() {
     // Test creating .gitignore when it doesn't exist
     assert!(utils::ensure_backups_in_gitignore(temp_path).is_ok());
 
-    // Check that .gitignore was created with *.backup
+    // Check that .gitignore was created with .depup/
     let gitignore_content = fs::read_to_string(temp_path.join(".gitignore")).unwrap();
-    assert!(gitignore_content.contains("*.backup"));
+    assert!(gitignore_content.contains(".depup/"));
 
     // Test adding to existing .gitignore
     let mut file = File::create(temp_path.join(".gitignore")).unwrap();
@@ -40,10 +40,10 @@ fn test_ensure_backups_in_gitignore() {
     // Run ensure_backups_in_gitignore again
     assert!(utils::ensure_backups_in_gitignore(temp_path).is_ok());
 
-    // Check that .gitignore still contains *.backup
+    // Check that .gitignore still contains .depup/
     let gitignore_content = fs::read_to_string(temp_path.join(".gitignore")).unwrap();
     assert!(gitignore_content.contains("node_modules/"));
-    assert!(gitignore_content.contains("*.backup"));
+    assert!(gitignore_content.contains(".depup/"));
 
     // Test with non-git directory
     let non_git_dir = tempdir().unwrap();
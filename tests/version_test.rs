@@ -0,0 +1,96 @@
+use depup::version::{self, Level, UpdateKind};
+
+#[test]
+fn test_parse_lenient_versions() {
+    assert_eq!(version::parse("1.2.3").unwrap().to_string(), "1.2.3");
+    assert_eq!(version::parse("^1.2").unwrap().to_string(), "1.2.0");
+    assert_eq!(version::parse("~4").unwrap().to_string(), "4.0.0");
+    assert_eq!(version::parse(">=2.1.0").unwrap().to_string(), "2.1.0");
+    assert_eq!(version::parse("v3.0.1").unwrap().to_string(), "3.0.1");
+    assert_eq!(
+        version::parse("2.0-beta.1").unwrap().to_string(),
+        "2.0.0-beta.1"
+    );
+    assert!(version::parse("dev-main").is_none());
+    assert!(version::parse("*").is_none());
+}
+
+#[test]
+fn test_classify() {
+    assert_eq!(version::classify("2.0.0", "3.0.0"), Some(UpdateKind::Major));
+    assert_eq!(version::classify("4", "4.4.8"), Some(UpdateKind::Minor));
+    assert_eq!(
+        version::classify("^1.0.1", "1.0.190"),
+        Some(UpdateKind::Patch)
+    );
+    assert_eq!(version::classify("1.0.0", "1.0.0"), None);
+    assert_eq!(version::classify("2.0.0", "1.9.0"), None);
+    assert_eq!(version::classify("latest", "1.0.0"), None);
+}
+
+#[test]
+fn test_ranges_from_every_ecosystem() {
+    // npm
+    assert_eq!(version::satisfies("18.3.1", "18.x"), Some(true));
+    assert_eq!(version::satisfies("1.5.0", ">=1.0 <2.0"), Some(true));
+    assert_eq!(version::satisfies("3.1.0", "^1 || ^3"), Some(true));
+    assert_eq!(version::satisfies("2.0.0", "^1 || ^3"), Some(false));
+    // Composer
+    assert_eq!(version::satisfies("6.4.2", "^5.4|^6.0"), Some(true));
+    assert_eq!(version::satisfies("5.4.9", ">= 5.4, < 6"), Some(true));
+    // Cargo
+    assert_eq!(version::satisfies("0.23.0", "<0.23"), Some(false));
+
+    assert!(version::parse_range("not a range").is_none());
+    assert!(version::parse_range("").is_none());
+}
+
+#[test]
+fn test_level_bounds() {
+    assert!(Level::Patch.allows("1.2.3", "1.2.9"));
+    assert!(!Level::Patch.allows("1.2.3", "1.3.0"));
+    assert!(Level::Minor.allows("1.2.3", "1.9.0"));
+    assert!(!Level::Minor.allows("1.2.3", "2.0.0"));
+    assert!(Level::Major.allows("1.2.3", "7.0.0"));
+
+    // Compatible follows caret semantics, which are stricter below 1.0
+    assert!(Level::Compatible.allows("1.2.3", "1.9.0"));
+    assert!(!Level::Compatible.allows("1.2.3", "2.0.0"));
+    assert!(Level::Compatible.allows("0.22.1", "0.22.9"));
+    assert!(!Level::Compatible.allows("0.22.1", "0.23.0"));
+    assert!(!Level::Compatible.allows("0.0.3", "0.0.4"));
+
+    // Versions that cannot be compared are not held back
+    assert!(Level::Patch.allows("dev-main", "2.0.0"));
+}
+
+#[test]
+fn test_prerelease_ordering() {
+    // Numeric identifiers compare as numbers, and pre-releases sort before
+    // the release they lead up to
+    assert!(version::is_newer("1.0.0-rc.10", "1.0.0-rc.9"));
+    assert!(version::is_newer("1.0.0-rc.1", "1.0.0-beta.11"));
+    assert!(version::is_newer("1.0.0", "1.0.0-rc.1"));
+    assert!(!version::is_newer("1.0.0-alpha", "1.0.0"));
+
+    // Composer's suffixes are normalized first
+    assert_eq!(
+        version::parse("2.0.0-RC10").unwrap().to_string(),
+        "2.0.0-rc.10"
+    );
+    assert!(version::is_newer("2.0.0-RC1", "2.0.0-beta2"));
+    assert!(version::is_newer("2.0.0-beta10", "2.0.0-beta9"));
+
+    // Unparseable versions only differ
+    assert!(version::is_newer("dev-main", "1.0.0"));
+    assert!(!version::is_newer("dev-main", "dev-main"));
+}
+
+#[test]
+fn test_prerelease_channel() {
+    assert!(version::is_prerelease("^2.0.0-beta.1"));
+    assert!(!version::is_prerelease("2.0.0"));
+    assert_eq!(version::channel("2.0.0-beta.3").as_deref(), Some("beta"));
+    assert_eq!(version::channel("2.0.0-RC1").as_deref(), Some("rc"));
+    assert_eq!(version::channel("2.0.0"), None);
+}